@@ -0,0 +1,78 @@
+use chrono::NaiveDate;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use rust_decimal::Decimal;
+
+use oneiro::frequency::Frequency;
+use oneiro::portfolio::{LoanSummary, Portfolio, PortfolioRow};
+use oneiro::repayment::RepaymentProfile;
+use oneiro::{CurrencyCode, Loan, RateLimits, RateSchedule};
+
+// A book of 30 year monthly annuities with spread out start dates, amounts
+// and rates, the same on every run
+fn book(loans: usize) -> Vec<PortfolioRow> {
+    (0..loans)
+        .map(|index| {
+            let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()
+                + chrono::Duration::days((index % 365) as i64);
+            let end_date = start_date
+                .checked_add_months(chrono::Months::new(360))
+                .unwrap();
+            let mut loan = Loan::new(
+                start_date,
+                end_date,
+                Decimal::from(100_000 + (index % 50) * 10_000),
+                RateSchedule::flat(Decimal::new(300 + (index % 20) as i64 * 10, 2)),
+                Decimal::new(150 + (index % 10) as i64 * 5, 2),
+                CurrencyCode::GBP,
+            );
+            loan.repayment = RepaymentProfile::Annuity;
+            loan.payment_frequency = Some(Frequency::Monthly);
+            PortfolioRow {
+                line: index as u64 + 2,
+                id: format!("loan-{}", index),
+                loan: Ok(loan),
+            }
+        })
+        .collect()
+}
+
+// Books calculated with `run` on one thread and on every CPU
+fn bench_books<R>(c: &mut Criterion, name: &str, run: R)
+where
+    R: Fn(&Portfolio, &RateLimits) -> Vec<LoanSummary>,
+{
+    let limits = RateLimits::default();
+    let cpus = std::thread::available_parallelism().map_or(1, usize::from);
+    let mut group = c.benchmark_group(name);
+    group.sample_size(10);
+    for loans in [10_000, 100_000] {
+        let mut portfolio = Portfolio::new(book(loans));
+        group.throughput(Throughput::Elements(loans as u64));
+        for threads in [1, cpus] {
+            portfolio.threads = Some(threads);
+            group.bench_with_input(
+                BenchmarkId::new(format!("{} threads", threads), loans),
+                &portfolio,
+                |b, portfolio| b.iter(|| run(portfolio, &limits)),
+            );
+        }
+    }
+    group.finish();
+}
+
+// Daily schedules built for every loan
+fn calculate(c: &mut Criterion) {
+    bench_books(c, "portfolio", |portfolio, limits| {
+        portfolio.calculate(limits, |_, _| Ok(()))
+    });
+}
+
+// Totals accrued a run of days at a time, without schedules
+fn total_interest(c: &mut Criterion) {
+    bench_books(c, "portfolio totals", |portfolio, limits| {
+        portfolio.total_interest(limits)
+    });
+}
+
+criterion_group!(benches, calculate, total_interest);
+criterion_main!(benches);
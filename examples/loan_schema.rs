@@ -0,0 +1,11 @@
+//! Prints the JSON Schema of a loan, as committed in `schema/loan.schema.json`
+//!
+//! `cargo run --example loan_schema --features schema > schema/loan.schema.json`
+
+fn main() {
+    let schema = schemars::schema_for!(oneiro::Loan);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema).expect("schema serializes")
+    );
+}
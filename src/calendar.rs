@@ -0,0 +1,379 @@
+use std::{collections::BTreeSet, error::Error, fmt::Display, fs, path::Path};
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// Rule for moving a date that falls on a non-business day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BusinessDayConvention {
+    /// The next business day
+    Following,
+    /// The next business day, unless that is in the next month, in which
+    /// case the previous business day
+    ModifiedFollowing,
+    /// The previous business day
+    Preceding,
+    /// The previous business day, unless that is in the previous month, in
+    /// which case the next business day
+    ModifiedPreceding,
+    /// The date is left as it is
+    #[default]
+    Unadjusted,
+}
+
+impl Display for BusinessDayConvention {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BusinessDayConvention::Following => f.write_str("following"),
+            BusinessDayConvention::ModifiedFollowing => f.write_str("modified-following"),
+            BusinessDayConvention::Preceding => f.write_str("preceding"),
+            BusinessDayConvention::ModifiedPreceding => f.write_str("modified-preceding"),
+            BusinessDayConvention::Unadjusted => f.write_str("unadjusted"),
+        }
+    }
+}
+
+/// Business day convention that is not recognised
+#[derive(Debug)]
+pub struct UnknownBusinessDayConventionError {
+    convention: String,
+}
+
+impl UnknownBusinessDayConventionError {
+    fn new(convention: String) -> Self {
+        Self { convention }
+    }
+}
+
+impl Display for UnknownBusinessDayConventionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error unknown business day convention: {}",
+            self.convention
+        ))
+    }
+}
+
+impl Error for UnknownBusinessDayConventionError {}
+
+impl TryFrom<&str> for BusinessDayConvention {
+    type Error = UnknownBusinessDayConventionError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().replace(['_', ' '], "-").as_str() {
+            "following" | "f" => Ok(BusinessDayConvention::Following),
+            "modified-following" | "mf" => Ok(BusinessDayConvention::ModifiedFollowing),
+            "preceding" | "p" => Ok(BusinessDayConvention::Preceding),
+            "modified-preceding" | "mp" => Ok(BusinessDayConvention::ModifiedPreceding),
+            "unadjusted" | "none" => Ok(BusinessDayConvention::Unadjusted),
+            _ => Err(UnknownBusinessDayConventionError::new(value.into())),
+        }
+    }
+}
+
+/// Days other than weekends on which payments are not made
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HolidayCalendar {
+    /// Name of the calendar, the file stem for calendars read from a file
+    pub name: String,
+    holidays: BTreeSet<NaiveDate>,
+}
+
+/// Error reading a holiday file, with the line it occurred on
+#[derive(Debug)]
+pub struct CalendarError {
+    line: usize,
+    message: String,
+}
+
+impl CalendarError {
+    fn new(line: usize, message: String) -> Self {
+        Self { line, message }
+    }
+
+    /// Line of the file the error occurred on, 0 when it is not tied to a line
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl Display for CalendarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error reading holidays on line {}: {}",
+            self.line, self.message
+        ))
+    }
+}
+
+impl Error for CalendarError {}
+
+impl HolidayCalendar {
+    /// A calendar where only Saturdays and Sundays are non-business days
+    pub fn weekends_only() -> Self {
+        Self {
+            name: "weekends".into(),
+            holidays: BTreeSet::new(),
+        }
+    }
+
+    /// Reads holidays from a file with one YYYY-MM-DD date per line. Anything
+    /// after a comma is taken as the holiday's description, and blank lines
+    /// and lines starting with `#` are skipped. The calendar is named after
+    /// the file.
+    pub fn from_file(path: &Path) -> Result<Self, CalendarError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| CalendarError::new(0, e.to_string()))?;
+        let mut holidays = BTreeSet::new();
+        for (index, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let date = line.split(',').next().unwrap_or_default().trim();
+            let holiday = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|e| CalendarError::new(index + 1, format!("{}: {:?}", e, date)))?;
+            holidays.insert(holiday);
+        }
+        let name = path
+            .file_stem()
+            .map_or_else(|| "holidays".into(), |stem| stem.to_string_lossy().into());
+        Ok(Self { name, holidays })
+    }
+
+    /// A calendar closed whenever any of `calendars` is closed
+    pub fn combine(calendars: impl IntoIterator<Item = HolidayCalendar>) -> Self {
+        calendars
+            .into_iter()
+            .reduce(|mut combined, calendar| {
+                combined.name = format!("{}+{}", combined.name, calendar.name);
+                combined.holidays.extend(calendar.holidays);
+                combined
+            })
+            .unwrap_or_else(Self::weekends_only)
+    }
+
+    /// Whether `date` is neither a weekend nor a holiday
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.holidays.contains(&date)
+    }
+
+    fn next_business_day(&self, mut date: NaiveDate) -> NaiveDate {
+        while !self.is_business_day(date) {
+            date += Duration::days(1);
+        }
+        date
+    }
+
+    fn previous_business_day(&self, mut date: NaiveDate) -> NaiveDate {
+        while !self.is_business_day(date) {
+            date -= Duration::days(1);
+        }
+        date
+    }
+
+    /// Moves `date` onto a business day under `convention`
+    pub fn adjust(&self, date: NaiveDate, convention: BusinessDayConvention) -> NaiveDate {
+        match convention {
+            BusinessDayConvention::Following => self.next_business_day(date),
+            BusinessDayConvention::ModifiedFollowing => {
+                let following = self.next_business_day(date);
+                if following.month() == date.month() {
+                    following
+                } else {
+                    self.previous_business_day(date)
+                }
+            }
+            BusinessDayConvention::Preceding => self.previous_business_day(date),
+            BusinessDayConvention::ModifiedPreceding => {
+                let preceding = self.previous_business_day(date);
+                if preceding.month() == date.month() {
+                    preceding
+                } else {
+                    self.next_business_day(date)
+                }
+            }
+            BusinessDayConvention::Unadjusted => date,
+        }
+    }
+
+    /// The date `days` business days after `date`
+    pub fn add_business_days(&self, mut date: NaiveDate, days: usize) -> NaiveDate {
+        for _ in 0..days {
+            date = self.next_business_day(date + Duration::days(1));
+        }
+        date
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Writes `contents` to a file in the temporary directory, named after
+    // the process so that concurrent test runs do not share files
+    fn holidays_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oneiro-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // Good Friday and Easter Monday 2024, either side of a month end
+    fn easter_2024() -> HolidayCalendar {
+        HolidayCalendar::from_file(&holidays_file(
+            "easter.txt",
+            "# Easter\n2024-03-29,Good Friday\n\n2024-04-01, Easter Monday\n",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn weekends_at_a_month_end_roll_by_convention() {
+        let calendar = HolidayCalendar::weekends_only();
+        // Saturday 30 September 2023
+        let saturday = date(2023, 9, 30);
+        let adjust = |convention| calendar.adjust(saturday, convention);
+        assert_eq!(adjust(BusinessDayConvention::Following), date(2023, 10, 2));
+        assert_eq!(
+            adjust(BusinessDayConvention::ModifiedFollowing),
+            date(2023, 9, 29)
+        );
+        assert_eq!(adjust(BusinessDayConvention::Preceding), date(2023, 9, 29));
+        assert_eq!(
+            adjust(BusinessDayConvention::ModifiedPreceding),
+            date(2023, 9, 29)
+        );
+        assert_eq!(adjust(BusinessDayConvention::Unadjusted), saturday);
+    }
+
+    #[test]
+    fn weekends_at_a_month_start_roll_by_convention() {
+        let calendar = HolidayCalendar::weekends_only();
+        // Sunday 1 October 2023
+        let sunday = date(2023, 10, 1);
+        let adjust = |convention| calendar.adjust(sunday, convention);
+        assert_eq!(adjust(BusinessDayConvention::Following), date(2023, 10, 2));
+        assert_eq!(
+            adjust(BusinessDayConvention::ModifiedFollowing),
+            date(2023, 10, 2)
+        );
+        assert_eq!(adjust(BusinessDayConvention::Preceding), date(2023, 9, 29));
+        assert_eq!(
+            adjust(BusinessDayConvention::ModifiedPreceding),
+            date(2023, 10, 2)
+        );
+    }
+
+    #[test]
+    fn holidays_next_to_weekends_are_rolled_over_too() {
+        let calendar = easter_2024();
+        assert_eq!(
+            calendar.name,
+            format!("oneiro-{}-easter", std::process::id())
+        );
+        let saturday = date(2024, 3, 30);
+        assert_eq!(
+            calendar.adjust(saturday, BusinessDayConvention::Following),
+            date(2024, 4, 2)
+        );
+        assert_eq!(
+            calendar.adjust(saturday, BusinessDayConvention::ModifiedFollowing),
+            date(2024, 3, 28)
+        );
+        assert_eq!(
+            calendar.adjust(date(2024, 4, 1), BusinessDayConvention::ModifiedPreceding),
+            date(2024, 4, 2)
+        );
+        assert_eq!(
+            calendar.adjust(date(2024, 3, 28), BusinessDayConvention::Following),
+            date(2024, 3, 28)
+        );
+    }
+
+    #[test]
+    fn business_days_are_added_around_weekends_and_holidays() {
+        let calendar = easter_2024();
+        assert_eq!(
+            calendar.add_business_days(date(2024, 3, 28), 0),
+            date(2024, 3, 28)
+        );
+        assert_eq!(
+            calendar.add_business_days(date(2024, 3, 28), 1),
+            date(2024, 4, 2)
+        );
+        assert_eq!(
+            calendar.add_business_days(date(2024, 3, 27), 3),
+            date(2024, 4, 3)
+        );
+    }
+
+    #[test]
+    fn combined_calendars_are_closed_when_any_is() {
+        let christmas =
+            HolidayCalendar::from_file(&holidays_file("christmas.txt", "2024-12-25\n")).unwrap();
+        let combined = HolidayCalendar::combine([easter_2024(), christmas]);
+        assert!(!combined.is_business_day(date(2024, 3, 29)));
+        assert!(!combined.is_business_day(date(2024, 12, 25)));
+        assert!(combined.is_business_day(date(2024, 12, 24)));
+        assert_eq!(
+            combined.name,
+            format!("oneiro-{0}-easter+oneiro-{0}-christmas", std::process::id())
+        );
+        assert_eq!(
+            HolidayCalendar::combine([]),
+            HolidayCalendar::weekends_only()
+        );
+    }
+
+    #[test]
+    fn holiday_file_errors_name_their_line() {
+        let error = HolidayCalendar::from_file(&holidays_file(
+            "bad-holiday.txt",
+            "2024-12-25\n\n25/12/2024,Christmas\n",
+        ))
+        .unwrap_err();
+        assert_eq!(error.line(), 3);
+        assert!(error
+            .to_string()
+            .starts_with("Error reading holidays on line 3: "));
+    }
+
+    #[test]
+    fn conventions_parse_and_display() {
+        for convention in [
+            BusinessDayConvention::Following,
+            BusinessDayConvention::ModifiedFollowing,
+            BusinessDayConvention::Preceding,
+            BusinessDayConvention::ModifiedPreceding,
+            BusinessDayConvention::Unadjusted,
+        ] {
+            assert_eq!(
+                BusinessDayConvention::try_from(convention.to_string().as_str()).unwrap(),
+                convention
+            );
+        }
+        assert_eq!(
+            BusinessDayConvention::try_from("Modified Following").unwrap(),
+            BusinessDayConvention::ModifiedFollowing
+        );
+        assert_eq!(
+            BusinessDayConvention::try_from("MP").unwrap(),
+            BusinessDayConvention::ModifiedPreceding
+        );
+        assert_eq!(
+            BusinessDayConvention::try_from("nearest")
+                .unwrap_err()
+                .to_string(),
+            "Error unknown business day convention: nearest"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rust_decimal::{Decimal, MathematicalOps};
+
+/// How accrued interest is added to the interest-bearing balance
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum CompoundingMethod {
+    /// Interest never capitalises, the balance stays at the principal
+    #[default]
+    Simple,
+    /// Each day's interest is capitalised at the end of that day
+    Daily,
+    /// Accrued interest is capitalised every month from the start date
+    Monthly,
+    /// Accrued interest is capitalised every three months from the start date
+    Quarterly,
+    /// Accrued interest is capitalised on each anniversary of the start date
+    Annual,
+    /// The balance grows continuously at `e^(r * t)`
+    Continuous,
+}
+
+impl Display for CompoundingMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompoundingMethod::Simple => f.write_str("simple"),
+            CompoundingMethod::Daily => f.write_str("daily"),
+            CompoundingMethod::Monthly => f.write_str("monthly"),
+            CompoundingMethod::Quarterly => f.write_str("quarterly"),
+            CompoundingMethod::Annual => f.write_str("annual"),
+            CompoundingMethod::Continuous => f.write_str("continuous"),
+        }
+    }
+}
+
+/// Compounding method that is not recognised
+#[derive(Debug)]
+pub struct UnknownCompoundingError {
+    compounding: String,
+}
+
+impl UnknownCompoundingError {
+    fn new(compounding: String) -> Self {
+        Self { compounding }
+    }
+}
+
+impl Display for UnknownCompoundingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error unknown compounding method: {}",
+            self.compounding
+        ))
+    }
+}
+
+impl Error for UnknownCompoundingError {}
+
+impl TryFrom<&str> for CompoundingMethod {
+    type Error = UnknownCompoundingError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "simple" => Ok(CompoundingMethod::Simple),
+            "daily" => Ok(CompoundingMethod::Daily),
+            "monthly" => Ok(CompoundingMethod::Monthly),
+            "quarterly" => Ok(CompoundingMethod::Quarterly),
+            "annual" | "annually" => Ok(CompoundingMethod::Annual),
+            "continuous" => Ok(CompoundingMethod::Continuous),
+            _ => Err(UnknownCompoundingError::new(value.into())),
+        }
+    }
+}
+
+impl CompoundingMethod {
+    /// Interest accrued on one unit of balance over `year_fraction` at an
+    /// annual `rate` given as a percentage
+    pub fn growth(&self, rate: Decimal, year_fraction: Decimal) -> Decimal {
+        let rate = rate / Decimal::from(100);
+        match self {
+            CompoundingMethod::Continuous => (rate * year_fraction).exp() - Decimal::ONE,
+            _ => rate * year_fraction,
+        }
+    }
+
+    /// Whether interest accrued up to and including `accrual_date` is added
+    /// to the balance at the end of that day
+    pub fn capitalises(&self, start_date: NaiveDate, accrual_date: NaiveDate) -> bool {
+        let months = match self {
+            CompoundingMethod::Simple => return false,
+            CompoundingMethod::Daily | CompoundingMethod::Continuous => return true,
+            CompoundingMethod::Monthly => 1,
+            CompoundingMethod::Quarterly => 3,
+            CompoundingMethod::Annual => 12,
+        };
+        let next_date = accrual_date + Duration::days(1);
+        let months_elapsed = (next_date.year() - start_date.year()) * 12 + next_date.month() as i32
+            - start_date.month() as i32;
+        months_elapsed > 0
+            && months_elapsed % months == 0
+            && start_date + Months::new(months_elapsed as u32) == next_date
+    }
+
+    /// First day on or after `date` whose accrued interest is capitalised,
+    /// `None` when interest never capitalises
+    pub(crate) fn next_capitalisation(
+        &self,
+        start_date: NaiveDate,
+        date: NaiveDate,
+    ) -> Option<NaiveDate> {
+        let months = match self {
+            CompoundingMethod::Simple => return None,
+            CompoundingMethod::Daily | CompoundingMethod::Continuous => return Some(date),
+            CompoundingMethod::Monthly => 1,
+            CompoundingMethod::Quarterly => 3,
+            CompoundingMethod::Annual => 12,
+        };
+        let months_elapsed = (date.year() - start_date.year()) * 12 + date.month() as i32
+            - start_date.month() as i32;
+        let mut periods = (months_elapsed / months).max(1) as u32;
+        loop {
+            let capitalised = start_date + Months::new(periods * months as u32) - Duration::days(1);
+            if capitalised >= date {
+                return Some(capitalised);
+            }
+            periods += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::RoundingStrategy;
+
+    use super::*;
+    use crate::currency::CurrencyCode;
+    use crate::day_count::DayCountConvention;
+    use crate::loan::Loan;
+    use crate::rate_schedule::RateSchedule;
+    use crate::rounding::{RoundingPolicy, RoundingStage};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Interest on 1,000,000 at 5% ACT/365F from 2023-01-01 to 2023-12-31,
+    // rounded only in total
+    fn total_interest(compounding: CompoundingMethod) -> Decimal {
+        let loan = Loan::new(
+            date(2023, 1, 1),
+            date(2023, 12, 31),
+            Decimal::from(1_000_000),
+            RateSchedule::flat(Decimal::from(5)),
+            Decimal::ZERO,
+            CurrencyCode::GBP,
+        )
+        .with_day_count(DayCountConvention::Act365Fixed)
+        .with_compounding(compounding)
+        .with_rounding(RoundingPolicy {
+            stage: RoundingStage::Total,
+            ..RoundingPolicy::default()
+        });
+        loan.total_interest().unwrap().unwrap().with_margin.value
+    }
+
+    fn cents(value: Decimal) -> Decimal {
+        value.round_dp_with_strategy(2, RoundingStrategy::MidpointNearestEven)
+    }
+
+    #[test]
+    fn growth_is_simple_unless_continuous() {
+        let half_year = Decimal::new(5, 1);
+        assert_eq!(
+            CompoundingMethod::Monthly.growth(Decimal::from(4), half_year),
+            Decimal::new(2, 2)
+        );
+        let continuous = CompoundingMethod::Continuous.growth(Decimal::from(4), half_year);
+        // e^0.02 - 1
+        assert_eq!(continuous.round_dp(10), Decimal::new(202_013_400, 10));
+    }
+
+    #[test]
+    fn capitalisation_falls_on_the_day_before_each_anniversary() {
+        let start = date(2023, 1, 15);
+        let monthly = CompoundingMethod::Monthly;
+        assert!(!monthly.capitalises(start, date(2023, 1, 15)));
+        assert!(monthly.capitalises(start, date(2023, 2, 14)));
+        assert!(!monthly.capitalises(start, date(2023, 2, 15)));
+        assert!(monthly.capitalises(start, date(2023, 3, 14)));
+
+        let quarterly = CompoundingMethod::Quarterly;
+        assert!(!quarterly.capitalises(start, date(2023, 2, 14)));
+        assert!(quarterly.capitalises(start, date(2023, 4, 14)));
+
+        let annual = CompoundingMethod::Annual;
+        assert!(!annual.capitalises(start, date(2023, 7, 14)));
+        assert!(annual.capitalises(start, date(2024, 1, 14)));
+
+        assert!(!CompoundingMethod::Simple.capitalises(start, date(2024, 1, 14)));
+        assert!(CompoundingMethod::Daily.capitalises(start, date(2023, 1, 15)));
+        assert!(CompoundingMethod::Continuous.capitalises(start, date(2023, 1, 15)));
+    }
+
+    #[test]
+    fn capitalisation_from_a_month_end_clamps_to_shorter_months() {
+        let start = date(2024, 1, 31);
+        let monthly = CompoundingMethod::Monthly;
+        // 31 January plus a month is 29 February
+        assert!(monthly.capitalises(start, date(2024, 2, 28)));
+        assert!(!monthly.capitalises(start, date(2024, 2, 29)));
+        // and plus two months is 31 March
+        assert!(monthly.capitalises(start, date(2024, 3, 30)));
+        assert_eq!(
+            monthly.next_capitalisation(start, date(2024, 2, 1)),
+            Some(date(2024, 2, 28))
+        );
+    }
+
+    #[test]
+    fn next_capitalisation_is_on_or_after_the_date() {
+        let start = date(2023, 1, 15);
+        let quarterly = CompoundingMethod::Quarterly;
+        assert_eq!(
+            quarterly.next_capitalisation(start, date(2023, 1, 15)),
+            Some(date(2023, 4, 14))
+        );
+        assert_eq!(
+            quarterly.next_capitalisation(start, date(2023, 4, 14)),
+            Some(date(2023, 4, 14))
+        );
+        assert_eq!(
+            quarterly.next_capitalisation(start, date(2023, 4, 15)),
+            Some(date(2023, 7, 14))
+        );
+        assert_eq!(
+            CompoundingMethod::Daily.next_capitalisation(start, date(2023, 6, 1)),
+            Some(date(2023, 6, 1))
+        );
+        assert_eq!(
+            CompoundingMethod::Simple.next_capitalisation(start, date(2023, 6, 1)),
+            None
+        );
+    }
+
+    #[test]
+    fn simple_interest_is_principal_rate_and_time() {
+        assert_eq!(
+            total_interest(CompoundingMethod::Simple),
+            Decimal::from(50_000)
+        );
+    }
+
+    #[test]
+    fn daily_compounding_grows_by_the_daily_rate_every_day() {
+        let daily = Decimal::ONE + Decimal::new(5, 2) / Decimal::from(365);
+        let expected = Decimal::from(1_000_000) * (daily.powu(365) - Decimal::ONE);
+        assert_eq!(total_interest(CompoundingMethod::Daily), cents(expected));
+    }
+
+    #[test]
+    fn monthly_compounding_grows_by_each_months_simple_interest() {
+        let expected = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31]
+            .iter()
+            .fold(Decimal::from(1_000_000), |balance, days| {
+                balance
+                    * (Decimal::ONE
+                        + Decimal::new(5, 2) * Decimal::from(*days) / Decimal::from(365))
+            })
+            - Decimal::from(1_000_000);
+        assert_eq!(total_interest(CompoundingMethod::Monthly), cents(expected));
+    }
+
+    #[test]
+    fn continuous_compounding_grows_by_e_to_the_rate() {
+        let expected = Decimal::from(1_000_000) * (Decimal::new(5, 2).exp() - Decimal::ONE);
+        // Each day's growth is an approximation of e^(r/365), so the total is
+        // only compared to the cent
+        let difference = total_interest(CompoundingMethod::Continuous) - expected;
+        assert!(difference.abs() <= Decimal::new(1, 2), "{}", difference);
+    }
+
+    #[test]
+    fn methods_parse_and_display() {
+        for method in [
+            CompoundingMethod::Simple,
+            CompoundingMethod::Daily,
+            CompoundingMethod::Monthly,
+            CompoundingMethod::Quarterly,
+            CompoundingMethod::Annual,
+            CompoundingMethod::Continuous,
+        ] {
+            assert_eq!(
+                CompoundingMethod::try_from(method.to_string().as_str()).unwrap(),
+                method
+            );
+        }
+        assert_eq!(
+            CompoundingMethod::try_from("Annually").unwrap(),
+            CompoundingMethod::Annual
+        );
+        assert_eq!(
+            CompoundingMethod::try_from("weekly")
+                .unwrap_err()
+                .to_string(),
+            "Error unknown compounding method: weekly"
+        );
+    }
+}
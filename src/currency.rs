@@ -0,0 +1,423 @@
+use std::{error::Error, fmt::Display};
+
+/// Declares `CurrencyCode` from the ISO 4217 table, one
+/// `CODE numeric minor_units symbol` row per currency. Funds, metals and
+/// other units without minor units use `-`, and currencies without a widely
+/// used symbol of their own use `-` for the symbol.
+macro_rules! currencies {
+    ($($code:ident $numeric:literal $minor_units:tt $symbol:tt,)+) => {
+        /// ISO 4217 currency, fund and precious metal codes
+        #[allow(clippy::upper_case_acronyms)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+        #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+        pub enum CurrencyCode {
+            $(
+                #[doc = concat!("ISO 4217 ", stringify!($code), ", numeric code ", stringify!($numeric))]
+                $code,
+            )+
+        }
+
+        /// Every code in the ISO 4217 list, in alphabetical order
+        pub const CURRENCIES: &[CurrencyCode] = &[$(CurrencyCode::$code,)+];
+
+        impl CurrencyCode {
+            /// Three letter alphabetic code
+            pub fn alphabetic(&self) -> &'static str {
+                match self {
+                    $(CurrencyCode::$code => stringify!($code),)+
+                }
+            }
+
+            /// Three digit numeric code
+            pub fn numeric(&self) -> u16 {
+                match self {
+                    $(CurrencyCode::$code => $numeric,)+
+                }
+            }
+
+            /// Number of decimal places of the minor unit, `None` where ISO
+            /// 4217 has no minor unit (funds, metals, SDRs and test codes)
+            pub fn minor_units(&self) -> Option<u32> {
+                match self {
+                    $(CurrencyCode::$code => currencies!(@minor_units $minor_units),)+
+                }
+            }
+
+            /// Local symbol, `None` where amounts are shown with the code
+            pub fn symbol(&self) -> Option<&'static str> {
+                match self {
+                    $(CurrencyCode::$code => currencies!(@symbol $symbol),)+
+                }
+            }
+        }
+    };
+    (@minor_units -) => { None };
+    (@minor_units $minor_units:literal) => { Some($minor_units) };
+    (@symbol -) => { None };
+    (@symbol $symbol:literal) => { Some($symbol) };
+}
+
+currencies! {
+    AED 784 2 "د.إ",
+    AFN 971 2 "؋",
+    ALL 8 2 "L",
+    AMD 51 2 "֏",
+    ANG 532 2 "ƒ",
+    AOA 973 2 "Kz",
+    ARS 32 2 -,
+    AUD 36 2 "A$",
+    AWG 533 2 "ƒ",
+    AZN 944 2 "₼",
+    BAM 977 2 "KM",
+    BBD 52 2 -,
+    BDT 50 2 "৳",
+    BGN 975 2 "лв",
+    BHD 48 3 -,
+    BIF 108 0 -,
+    BMD 60 2 -,
+    BND 96 2 -,
+    BOB 68 2 "Bs",
+    BOV 984 2 -,
+    BRL 986 2 "R$",
+    BSD 44 2 -,
+    BTN 64 2 "Nu.",
+    BWP 72 2 "P",
+    BYN 933 2 "Br",
+    BZD 84 2 -,
+    CAD 124 2 "C$",
+    CDF 976 2 -,
+    CHE 947 2 -,
+    CHF 756 2 -,
+    CHW 948 2 -,
+    CLF 990 4 -,
+    CLP 152 0 -,
+    CNY 156 2 "¥",
+    COP 170 2 -,
+    COU 970 2 -,
+    CRC 188 2 "₡",
+    CUC 931 2 -,
+    CUP 192 2 -,
+    CVE 132 2 -,
+    CZK 203 2 "Kč",
+    DJF 262 0 -,
+    DKK 208 2 "kr.",
+    DOP 214 2 "RD$",
+    DZD 12 2 -,
+    EGP 818 2 "E£",
+    ERN 232 2 "Nfk",
+    ETB 230 2 "Br",
+    EUR 978 2 "€",
+    FJD 242 2 -,
+    FKP 238 2 -,
+    GBP 826 2 "£",
+    GEL 981 2 "₾",
+    GHS 936 2 "₵",
+    GIP 292 2 -,
+    GMD 270 2 "D",
+    GNF 324 0 -,
+    GTQ 320 2 "Q",
+    GYD 328 2 -,
+    HKD 344 2 "HK$",
+    HNL 340 2 "L",
+    HTG 332 2 "G",
+    HUF 348 2 "Ft",
+    IDR 360 2 "Rp",
+    ILS 376 2 "₪",
+    INR 356 2 "₹",
+    IQD 368 3 -,
+    IRR 364 2 "﷼",
+    ISK 352 0 -,
+    JMD 388 2 -,
+    JOD 400 3 -,
+    JPY 392 0 "¥",
+    KES 404 2 "KSh",
+    KGS 417 2 -,
+    KHR 116 2 "៛",
+    KMF 174 0 -,
+    KPW 408 2 "₩",
+    KRW 410 0 "₩",
+    KWD 414 3 -,
+    KYD 136 2 -,
+    KZT 398 2 "₸",
+    LAK 418 2 "₭",
+    LBP 422 2 -,
+    LKR 144 2 -,
+    LRD 430 2 -,
+    LSL 426 2 -,
+    LYD 434 3 -,
+    MAD 504 2 -,
+    MDL 498 2 -,
+    MGA 969 2 "Ar",
+    MKD 807 2 "ден",
+    MMK 104 2 "K",
+    MNT 496 2 "₮",
+    MOP 446 2 "MOP$",
+    MRU 929 2 "UM",
+    MUR 480 2 -,
+    MVR 462 2 "Rf",
+    MWK 454 2 "MK",
+    MXN 484 2 "Mex$",
+    MXV 979 2 -,
+    MYR 458 2 "RM",
+    MZN 943 2 "MT",
+    NAD 516 2 -,
+    NGN 566 2 "₦",
+    NIO 558 2 "C$",
+    NOK 578 2 -,
+    NPR 524 2 -,
+    NZD 554 2 "NZ$",
+    OMR 512 3 -,
+    PAB 590 2 "B/.",
+    PEN 604 2 "S/",
+    PGK 598 2 "K",
+    PHP 608 2 "₱",
+    PKR 586 2 -,
+    PLN 985 2 "zł",
+    PYG 600 0 "₲",
+    QAR 634 2 -,
+    RON 946 2 "lei",
+    RSD 941 2 -,
+    RUB 643 2 "₽",
+    RWF 646 0 -,
+    SAR 682 2 -,
+    SBD 90 2 -,
+    SCR 690 2 -,
+    SDG 938 2 -,
+    SEK 752 2 -,
+    SGD 702 2 "S$",
+    SHP 654 2 -,
+    SLE 925 2 -,
+    SOS 706 2 -,
+    SRD 968 2 -,
+    SSP 728 2 -,
+    STN 930 2 "Db",
+    SVC 222 2 -,
+    SYP 760 2 -,
+    SZL 748 2 "E",
+    THB 764 2 "฿",
+    TJS 972 2 -,
+    TMT 934 2 "m",
+    TND 788 3 -,
+    TOP 776 2 "T$",
+    TRY 949 2 "₺",
+    TTD 780 2 "TT$",
+    TWD 901 2 "NT$",
+    TZS 834 2 -,
+    UAH 980 2 "₴",
+    UGX 800 0 -,
+    USD 840 2 "$",
+    USN 997 2 -,
+    UYI 940 0 -,
+    UYU 858 2 "$U",
+    UYW 927 4 -,
+    UZS 860 2 -,
+    VED 926 2 -,
+    VES 928 2 "Bs.S",
+    VND 704 0 "₫",
+    VUV 548 0 "VT",
+    WST 882 2 "WS$",
+    XAF 950 0 "FCFA",
+    XAG 961 - -,
+    XAU 959 - -,
+    XBA 955 - -,
+    XBB 956 - -,
+    XBC 957 - -,
+    XBD 958 - -,
+    XCD 951 2 "EC$",
+    XDR 960 - -,
+    XOF 952 0 "CFA",
+    XPD 964 - -,
+    XPF 953 0 "₣",
+    XPT 962 - -,
+    XSU 994 - -,
+    XTS 963 - -,
+    XUA 965 - -,
+    XXX 999 - -,
+    YER 886 2 -,
+    ZAR 710 2 "R",
+    ZMW 967 2 "K",
+    ZWG 924 2 "ZiG",
+}
+
+impl Display for CurrencyCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.alphabetic())
+    }
+}
+
+/// Currency code that is not in the ISO 4217 list
+#[derive(Debug)]
+pub struct UnknownCurrencyError {
+    currency_code: String,
+}
+
+impl UnknownCurrencyError {
+    fn new(currency_code: String) -> Self {
+        Self { currency_code }
+    }
+}
+
+impl Display for UnknownCurrencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error unknown currency code: {}",
+            self.currency_code
+        ))
+    }
+}
+
+impl Error for UnknownCurrencyError {}
+
+/// Accepts the alphabetic code in any case or the three digit numeric code
+impl TryFrom<&str> for CurrencyCode {
+    type Error = UnknownCurrencyError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let code = value.trim().to_uppercase();
+        let numeric = code.parse::<u16>().ok();
+        CURRENCIES
+            .iter()
+            .find(|currency| currency.alphabetic() == code || Some(currency.numeric()) == numeric)
+            .copied()
+            .ok_or_else(|| UnknownCurrencyError::new(value.into()))
+    }
+}
+
+/// Reads the alphabetic or numeric code as `TryFrom<&str>` does, so unknown
+/// codes fail with the same error. Numeric codes may also be written as
+/// numbers.
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CurrencyCode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(CurrencyCodeVisitor)
+    }
+}
+
+// Parses inside the deserializer so errors carry the position of the code
+#[cfg(feature = "serde")]
+struct CurrencyCodeVisitor;
+
+#[cfg(feature = "serde")]
+impl serde::de::Visitor<'_> for CurrencyCodeVisitor {
+    type Value = CurrencyCode;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("an ISO 4217 currency code")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        CurrencyCode::try_from(value).map_err(E::custom)
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visit_str(&value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rust_decimal::Decimal;
+
+    use super::*;
+    use crate::money::Money;
+
+    #[test]
+    fn minor_units_follow_iso_4217() {
+        assert_eq!(CurrencyCode::GBP.minor_units(), Some(2));
+        assert_eq!(CurrencyCode::JPY.minor_units(), Some(0));
+        assert_eq!(CurrencyCode::ISK.minor_units(), Some(0));
+        assert_eq!(CurrencyCode::KWD.minor_units(), Some(3));
+        assert_eq!(CurrencyCode::BHD.minor_units(), Some(3));
+        assert_eq!(CurrencyCode::CLF.minor_units(), Some(4));
+        assert_eq!(CurrencyCode::XAU.minor_units(), None);
+        assert_eq!(CurrencyCode::XXX.minor_units(), None);
+    }
+
+    #[test]
+    fn amounts_round_to_their_currencys_minor_units() {
+        let rounded = |code| Money::new(Decimal::new(12_345_678, 4), code).rounded();
+        assert_eq!(rounded(CurrencyCode::GBP).to_string(), "1234.57");
+        assert_eq!(rounded(CurrencyCode::JPY).to_string(), "1235");
+        assert_eq!(rounded(CurrencyCode::KWD).to_string(), "1234.568");
+        assert_eq!(rounded(CurrencyCode::CLF).to_string(), "1234.5678");
+        assert_eq!(rounded(CurrencyCode::XAU).to_string(), "1234.5678");
+        assert_eq!(
+            Money::new(Decimal::from(5), CurrencyCode::KWD)
+                .rounded()
+                .to_string(),
+            "5.000"
+        );
+    }
+
+    #[test]
+    fn codes_parse_alphabetic_in_any_case_or_numeric() {
+        assert_eq!(CurrencyCode::try_from("gbp").unwrap(), CurrencyCode::GBP);
+        assert_eq!(CurrencyCode::try_from(" JPY ").unwrap(), CurrencyCode::JPY);
+        assert_eq!(CurrencyCode::try_from("392").unwrap(), CurrencyCode::JPY);
+        assert_eq!(CurrencyCode::try_from("008").unwrap(), CurrencyCode::ALL);
+        assert_eq!(CurrencyCode::try_from("8").unwrap(), CurrencyCode::ALL);
+        assert_eq!(CurrencyCode::KWD.numeric(), 414);
+        assert_eq!(CurrencyCode::KWD.to_string(), "KWD");
+    }
+
+    #[test]
+    fn unknown_codes_are_errors() {
+        for code in ["ABC", "000", "GB", ""] {
+            assert_eq!(
+                CurrencyCode::try_from(code).unwrap_err().to_string(),
+                format!("Error unknown currency code: {}", code)
+            );
+        }
+    }
+
+    #[test]
+    fn codes_are_unique_and_in_alphabetical_order() {
+        assert!(CURRENCIES
+            .windows(2)
+            .all(|pair| pair[0].alphabetic() < pair[1].alphabetic()));
+        let numeric = CURRENCIES
+            .iter()
+            .map(CurrencyCode::numeric)
+            .collect::<HashSet<_>>();
+        assert_eq!(numeric.len(), CURRENCIES.len());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn codes_deserialize_from_strings_and_numbers() {
+        assert_eq!(
+            serde_json::from_str::<CurrencyCode>(r#""eur""#).unwrap(),
+            CurrencyCode::EUR
+        );
+        assert_eq!(
+            serde_json::from_str::<CurrencyCode>("978").unwrap(),
+            CurrencyCode::EUR
+        );
+        assert_eq!(
+            serde_json::to_string(&CurrencyCode::EUR).unwrap(),
+            r#""EUR""#
+        );
+        assert!(serde_json::from_str::<CurrencyCode>(r#""ABC""#)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Error unknown currency code: ABC"));
+    }
+}
@@ -0,0 +1,565 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use rust_decimal::Decimal;
+
+/// Day count conventions used to turn an accrual between two dates into a
+/// fraction of a year
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DayCountConvention {
+    /// Actual/365 Fixed
+    #[cfg_attr(feature = "serde", serde(rename = "ACT/365F"))]
+    Act365Fixed,
+    /// Actual/360
+    #[cfg_attr(feature = "serde", serde(rename = "ACT/360"))]
+    Act360,
+    /// Actual/365L (ICMA Rule 251.1(i))
+    #[cfg_attr(feature = "serde", serde(rename = "ACT/365L"))]
+    Act365Leap,
+    /// 30/360 US, bond basis with the SIA end of February rules
+    #[cfg_attr(feature = "serde", serde(rename = "30/360 US"))]
+    Thirty360Us,
+    /// 30E/360, Eurobond basis
+    #[cfg_attr(feature = "serde", serde(rename = "30E/360"))]
+    ThirtyE360,
+    /// 30E/360 ISDA, German
+    #[cfg_attr(feature = "serde", serde(rename = "30E/360 ISDA"))]
+    ThirtyE360Isda,
+    /// Actual/Actual ISDA
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "ACT/ACT ISDA"))]
+    ActActIsda,
+    /// Actual/Actual ICMA
+    #[cfg_attr(feature = "serde", serde(rename = "ACT/ACT ICMA"))]
+    ActActIcma,
+}
+
+impl Display for DayCountConvention {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DayCountConvention::Act365Fixed => f.write_str("ACT/365F"),
+            DayCountConvention::Act360 => f.write_str("ACT/360"),
+            DayCountConvention::Act365Leap => f.write_str("ACT/365L"),
+            DayCountConvention::Thirty360Us => f.write_str("30/360 US"),
+            DayCountConvention::ThirtyE360 => f.write_str("30E/360"),
+            DayCountConvention::ThirtyE360Isda => f.write_str("30E/360 ISDA"),
+            DayCountConvention::ActActIsda => f.write_str("ACT/ACT ISDA"),
+            DayCountConvention::ActActIcma => f.write_str("ACT/ACT ICMA"),
+        }
+    }
+}
+
+/// Day count convention that is not recognised
+#[derive(Debug)]
+pub struct UnknownDayCountError {
+    day_count: String,
+}
+
+impl UnknownDayCountError {
+    fn new(day_count: String) -> Self {
+        Self { day_count }
+    }
+}
+
+impl Display for UnknownDayCountError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error unknown day count convention: {}",
+            self.day_count
+        ))
+    }
+}
+
+impl Error for UnknownDayCountError {}
+
+impl TryFrom<&str> for DayCountConvention {
+    type Error = UnknownDayCountError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        // Ignore case and separators so "act/act isda" and "ACT/ACT-ISDA" both parse
+        let normalised: String = value
+            .to_uppercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '/')
+            .collect();
+        match normalised.as_str() {
+            "ACT/365F" | "ACT/365FIXED" | "ACTUAL/365FIXED" => Ok(DayCountConvention::Act365Fixed),
+            "ACT/360" | "ACTUAL/360" => Ok(DayCountConvention::Act360),
+            "ACT/365L" | "ACTUAL/365L" => Ok(DayCountConvention::Act365Leap),
+            "30/360" | "30/360US" | "30U/360" | "BONDBASIS" => Ok(DayCountConvention::Thirty360Us),
+            "30E/360" | "EUROBONDBASIS" => Ok(DayCountConvention::ThirtyE360),
+            "30E/360ISDA" | "GERMAN" => Ok(DayCountConvention::ThirtyE360Isda),
+            "ACT/ACT" | "ACT/ACTISDA" | "ACTUAL/ACTUALISDA" => Ok(DayCountConvention::ActActIsda),
+            "ACT/ACTICMA" | "ACTUAL/ACTUALICMA" => Ok(DayCountConvention::ActActIcma),
+            _ => Err(UnknownDayCountError::new(value.into())),
+        }
+    }
+}
+
+/// The regular interest period an accrual falls in. Only ACT/ACT ICMA,
+/// ACT/365L and 30E/360 ISDA look beyond the two accrual dates.
+#[derive(Debug, Clone, Copy)]
+pub struct ReferencePeriod {
+    /// First day of the reference period
+    pub start: NaiveDate,
+    /// Day after the last day of the reference period
+    pub end: NaiveDate,
+    /// Number of regular periods per year
+    pub frequency: u32,
+    /// Termination date of the loan
+    pub maturity: NaiveDate,
+}
+
+fn is_leap_year(year: i32) -> bool {
+    NaiveDate::from_ymd_opt(year, 2, 29).is_some()
+}
+
+fn days_in_calendar_year(year: i32) -> i64 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+pub(crate) fn is_last_day_of_month(date: NaiveDate) -> bool {
+    date.succ_opt()
+        .map_or(true, |next| next.month() != date.month())
+}
+
+fn is_last_day_of_february(date: NaiveDate) -> bool {
+    date.month() == 2 && is_last_day_of_month(date)
+}
+
+fn actual_days(start: NaiveDate, end: NaiveDate) -> i64 {
+    end.signed_duration_since(start).num_days()
+}
+
+// 360 * (Y2 - Y1) + 30 * (M2 - M1) + (D2 - D1), with the days already adjusted
+fn thirty_360_days(start: NaiveDate, end: NaiveDate, d1: u32, d2: u32) -> i64 {
+    360 * (end.year() - start.year()) as i64
+        + 30 * (end.month() as i64 - start.month() as i64)
+        + (d2 as i64 - d1 as i64)
+}
+
+// Does the period (start, end] contain a 29th of February
+fn contains_leap_day(start: NaiveDate, end: NaiveDate) -> bool {
+    (start.year()..=end.year()).any(|year| {
+        NaiveDate::from_ymd_opt(year, 2, 29)
+            .is_some_and(|leap_day| start < leap_day && leap_day <= end)
+    })
+}
+
+impl DayCountConvention {
+    // Days between `start` and `end` counted with the 30/360 month end rules,
+    // the actual days under the other conventions
+    fn days_between(&self, start: NaiveDate, end: NaiveDate, period: &ReferencePeriod) -> i64 {
+        match self {
+            DayCountConvention::Thirty360Us => {
+                let mut d1 = start.day();
+                let mut d2 = end.day();
+                if is_last_day_of_february(start) {
+                    if is_last_day_of_february(end) {
+                        d2 = 30;
+                    }
+                    d1 = 30;
+                }
+                if d2 == 31 && d1 >= 30 {
+                    d2 = 30;
+                }
+                if d1 == 31 {
+                    d1 = 30;
+                }
+                thirty_360_days(start, end, d1, d2)
+            }
+            DayCountConvention::ThirtyE360 => {
+                let d1 = start.day().min(30);
+                let d2 = end.day().min(30);
+                thirty_360_days(start, end, d1, d2)
+            }
+            DayCountConvention::ThirtyE360Isda => {
+                let d1 = if is_last_day_of_month(start) {
+                    30
+                } else {
+                    start.day()
+                };
+                let d2 =
+                    if is_last_day_of_month(end) && !(end == period.maturity && end.month() == 2) {
+                        30
+                    } else {
+                        end.day()
+                    };
+                thirty_360_days(start, end, d1, d2)
+            }
+            _ => actual_days(start, end),
+        }
+    }
+
+    /// Length of the year, in days, that an accrual on `date` is divided by
+    pub fn days_in_year(&self, date: NaiveDate, period: &ReferencePeriod) -> u32 {
+        match self {
+            DayCountConvention::Act365Fixed => 365,
+            DayCountConvention::Act360
+            | DayCountConvention::Thirty360Us
+            | DayCountConvention::ThirtyE360
+            | DayCountConvention::ThirtyE360Isda => 360,
+            DayCountConvention::Act365Leap => {
+                // Annual periods use 366 when a leap day falls in the period,
+                // other frequencies look at the year the period ends in.
+                let leap = if period.frequency == 1 {
+                    contains_leap_day(period.start, period.end)
+                } else {
+                    is_leap_year(period.end.year())
+                };
+                if leap {
+                    366
+                } else {
+                    365
+                }
+            }
+            DayCountConvention::ActActIsda => days_in_calendar_year(date.year()) as u32,
+            DayCountConvention::ActActIcma => {
+                period.frequency * actual_days(period.start, period.end) as u32
+            }
+        }
+    }
+
+    /// Fraction of a year accrued between `start` (inclusive) and `end`
+    /// (exclusive)
+    pub fn year_fraction(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        period: &ReferencePeriod,
+    ) -> Decimal {
+        match self {
+            DayCountConvention::Act365Fixed => {
+                Decimal::from(actual_days(start, end)) / Decimal::from(365)
+            }
+            DayCountConvention::Act360 => {
+                Decimal::from(actual_days(start, end)) / Decimal::from(360)
+            }
+            DayCountConvention::Act365Leap => {
+                Decimal::from(actual_days(start, end))
+                    / Decimal::from(self.days_in_year(start, period))
+            }
+            DayCountConvention::Thirty360Us
+            | DayCountConvention::ThirtyE360
+            | DayCountConvention::ThirtyE360Isda => {
+                Decimal::from(self.days_between(start, end, period)) / Decimal::from(360)
+            }
+            DayCountConvention::ActActIsda => {
+                // Split the accrual at each 1st of January and weight the
+                // days by the length of the calendar year they fall in
+                let mut fraction = Decimal::ZERO;
+                let mut cursor = start;
+                while cursor < end {
+                    let next_year = NaiveDate::from_ymd_opt(cursor.year() + 1, 1, 1)
+                        .expect("date out of range");
+                    let segment_end = next_year.min(end);
+                    fraction += Decimal::from(actual_days(cursor, segment_end))
+                        / Decimal::from(days_in_calendar_year(cursor.year()));
+                    cursor = segment_end;
+                }
+                fraction
+            }
+            DayCountConvention::ActActIcma => {
+                let period_days = actual_days(period.start, period.end);
+                Decimal::from(actual_days(start, end))
+                    / Decimal::from(period.frequency as i64 * period_days)
+            }
+        }
+    }
+
+    /// Fraction of a year accrued on `date` alone, within its reference
+    /// `period`. The 30/360 conventions count the day as the growth of the
+    /// days since the start of the period, so the month end adjustments add
+    /// up to the period total, the others accrue one day of their year.
+    pub fn daily_year_fraction(&self, date: NaiveDate, period: &ReferencePeriod) -> Decimal {
+        match self {
+            DayCountConvention::Thirty360Us
+            | DayCountConvention::ThirtyE360
+            | DayCountConvention::ThirtyE360Isda => {
+                let next_date = date + Duration::days(1);
+                Decimal::from(
+                    self.days_between(period.start, next_date, period)
+                        - self.days_between(period.start, date, period),
+                ) / Decimal::from(360)
+            }
+            _ => Decimal::ONE / Decimal::from(self.days_in_year(date, period)),
+        }
+    }
+
+    /// Last day from `date` that accrues the same daily year fraction as
+    /// `date`, no later than the end of its reference `period`
+    pub(crate) fn same_daily_year_fraction_until(
+        &self,
+        date: NaiveDate,
+        period: &ReferencePeriod,
+    ) -> NaiveDate {
+        let period_end = period.end - Duration::days(1);
+        let last_day = match self {
+            DayCountConvention::Act365Fixed
+            | DayCountConvention::Act360
+            | DayCountConvention::Act365Leap
+            | DayCountConvention::ActActIcma => period_end,
+            DayCountConvention::ActActIsda => {
+                NaiveDate::from_ymd_opt(date.year(), 12, 31).expect("date out of range")
+            }
+            // The month end rules only move the days from the 27th on
+            DayCountConvention::Thirty360Us
+            | DayCountConvention::ThirtyE360
+            | DayCountConvention::ThirtyE360Isda => {
+                if date.day() >= 27 {
+                    date
+                } else {
+                    date.with_day(26).expect("day within month")
+                }
+            }
+        };
+        last_day.min(period_end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn period(start: NaiveDate, end: NaiveDate, frequency: u32) -> ReferencePeriod {
+        ReferencePeriod {
+            start,
+            end,
+            frequency,
+            maturity: end,
+        }
+    }
+
+    // The ISDA comparison of the ACT/ACT conventions, a regular semi-annual
+    // period from 1 November 2003 to 1 May 2004 of 182 days
+    fn isda_period() -> ReferencePeriod {
+        period(date(2003, 11, 1), date(2004, 5, 1), 2)
+    }
+
+    fn year_fraction(day_count: DayCountConvention, period: &ReferencePeriod) -> Decimal {
+        day_count.year_fraction(period.start, period.end, period)
+    }
+
+    // Year fraction of a 30/360 convention from `start` to `end`, within a
+    // period that matures well after them
+    fn thirty_360(day_count: DayCountConvention, start: NaiveDate, end: NaiveDate) -> Decimal {
+        day_count.year_fraction(start, end, &period(start, date(2030, 1, 1), 2))
+    }
+
+    fn days_360(days: i64) -> Decimal {
+        Decimal::from(days) / Decimal::from(360)
+    }
+
+    #[test]
+    fn act_act_isda_weights_days_by_their_calendar_year() {
+        let fraction = year_fraction(DayCountConvention::ActActIsda, &isda_period());
+        // 61 days of 2003 over 365 and 121 days of 2004 over 366
+        assert_eq!(fraction.round_dp(6), Decimal::new(497724, 6));
+        assert_eq!(
+            fraction,
+            Decimal::from(61) / Decimal::from(365) + Decimal::from(121) / Decimal::from(366)
+        );
+    }
+
+    #[test]
+    fn act_act_icma_divides_by_the_reference_period() {
+        let period = isda_period();
+        assert_eq!(
+            year_fraction(DayCountConvention::ActActIcma, &period),
+            Decimal::new(5, 1)
+        );
+        assert_eq!(
+            DayCountConvention::ActActIcma.days_in_year(period.start, &period),
+            364
+        );
+    }
+
+    #[test]
+    fn actual_conventions_divide_by_a_fixed_or_leap_year() {
+        let isda = isda_period();
+        assert_eq!(
+            year_fraction(DayCountConvention::Act365Fixed, &isda),
+            Decimal::from(182) / Decimal::from(365)
+        );
+        assert_eq!(
+            year_fraction(DayCountConvention::Act360, &isda).round_dp(6),
+            Decimal::new(505556, 6)
+        );
+        // A semi-annual period ending in the leap year 2004 uses 366 days
+        assert_eq!(
+            year_fraction(DayCountConvention::Act365Leap, &isda),
+            Decimal::from(182) / Decimal::from(366)
+        );
+        // An annual period uses 366 days only when it contains 29 February
+        let annual = period(date(2004, 3, 1), date(2005, 3, 1), 1);
+        assert_eq!(
+            DayCountConvention::Act365Leap.days_in_year(annual.start, &annual),
+            365
+        );
+        let annual = period(date(2003, 3, 1), date(2004, 3, 1), 1);
+        assert_eq!(
+            DayCountConvention::Act365Leap.days_in_year(annual.start, &annual),
+            366
+        );
+    }
+
+    #[test]
+    fn thirty_360_us_end_of_february() {
+        let day_count = DayCountConvention::Thirty360Us;
+        assert_eq!(
+            thirty_360(day_count, date(2007, 2, 28), date(2007, 3, 31)),
+            days_360(30)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2007, 2, 28), date(2008, 2, 29)),
+            days_360(360)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2007, 1, 31), date(2007, 2, 28)),
+            days_360(28)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2008, 2, 29), date(2008, 8, 31)),
+            days_360(180)
+        );
+        // The 31st only moves to the 30th when the start is on the 30th or 31st
+        assert_eq!(
+            thirty_360(day_count, date(2007, 3, 15), date(2007, 3, 31)),
+            days_360(16)
+        );
+    }
+
+    #[test]
+    fn thirty_e_360_end_of_february() {
+        let day_count = DayCountConvention::ThirtyE360;
+        assert_eq!(
+            thirty_360(day_count, date(2007, 2, 28), date(2007, 3, 31)),
+            days_360(32)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2007, 2, 28), date(2008, 2, 29)),
+            days_360(361)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2007, 1, 31), date(2007, 2, 28)),
+            days_360(28)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2008, 2, 29), date(2008, 8, 31)),
+            days_360(181)
+        );
+    }
+
+    #[test]
+    fn thirty_e_360_isda_end_of_february() {
+        let day_count = DayCountConvention::ThirtyE360Isda;
+        assert_eq!(
+            thirty_360(day_count, date(2007, 2, 28), date(2007, 3, 31)),
+            days_360(30)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2007, 2, 28), date(2008, 2, 29)),
+            days_360(360)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2007, 1, 31), date(2007, 2, 28)),
+            days_360(30)
+        );
+        assert_eq!(
+            thirty_360(day_count, date(2008, 2, 29), date(2008, 8, 31)),
+            days_360(180)
+        );
+    }
+
+    #[test]
+    fn thirty_e_360_isda_keeps_february_maturity_dates() {
+        let day_count = DayCountConvention::ThirtyE360Isda;
+        let to_maturity =
+            |start, maturity| day_count.year_fraction(start, maturity, &period(start, maturity, 2));
+        assert_eq!(
+            to_maturity(date(2007, 1, 31), date(2007, 2, 28)),
+            days_360(28)
+        );
+        assert_eq!(
+            to_maturity(date(2007, 2, 28), date(2008, 2, 29)),
+            days_360(359)
+        );
+        // Other month ends are still moved to the 30th at maturity
+        assert_eq!(
+            to_maturity(date(2008, 2, 29), date(2008, 8, 31)),
+            days_360(180)
+        );
+    }
+
+    #[test]
+    fn daily_fractions_add_up_to_the_period() {
+        let reference = period(date(2007, 1, 31), date(2008, 3, 1), 1);
+        for day_count in [
+            DayCountConvention::Thirty360Us,
+            DayCountConvention::ThirtyE360,
+            DayCountConvention::ThirtyE360Isda,
+        ] {
+            let mut total = Decimal::ZERO;
+            let mut date = reference.start;
+            while date < reference.end {
+                total += day_count.daily_year_fraction(date, &reference);
+                date += Duration::days(1);
+            }
+            // Equal but for the last digit of each day's division by 360
+            assert_eq!(
+                total.round_dp(20),
+                day_count
+                    .year_fraction(reference.start, reference.end, &reference)
+                    .round_dp(20),
+                "{}",
+                day_count
+            );
+        }
+    }
+
+    #[test]
+    fn runs_of_days_share_their_daily_fraction() {
+        let reference = period(date(2023, 12, 1), date(2024, 3, 1), 4);
+        for day_count in [
+            DayCountConvention::Act365Fixed,
+            DayCountConvention::Act360,
+            DayCountConvention::Act365Leap,
+            DayCountConvention::Thirty360Us,
+            DayCountConvention::ThirtyE360,
+            DayCountConvention::ThirtyE360Isda,
+            DayCountConvention::ActActIsda,
+            DayCountConvention::ActActIcma,
+        ] {
+            let mut date = reference.start;
+            while date < reference.end {
+                let until = day_count.same_daily_year_fraction_until(date, &reference);
+                assert!(
+                    until >= date && until < reference.end,
+                    "{} {}",
+                    day_count,
+                    date
+                );
+                let fraction = day_count.daily_year_fraction(date, &reference);
+                while date <= until {
+                    assert_eq!(
+                        day_count.daily_year_fraction(date, &reference),
+                        fraction,
+                        "{} {}",
+                        day_count,
+                        date
+                    );
+                    date += Duration::days(1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use std::{error::Error, fmt::Display, path::Path, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+/// A borrower event that changes the outstanding principal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "event", content = "amount"))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum LoanEventKind {
+    /// Repays part of the principal
+    Prepayment(Decimal),
+    /// Repays all of the outstanding principal
+    #[cfg_attr(feature = "serde", serde(rename = "repayment"))]
+    EarlyRepayment,
+    /// Draws down additional principal
+    Drawdown(Decimal),
+}
+
+/// An event taking effect from the start of its date, so that day already
+/// accrues on the new balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct LoanEvent {
+    /// Day the event takes effect, at the start of the day
+    pub date: NaiveDate,
+    /// What happens to the balance
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub kind: LoanEventKind,
+}
+
+/// Deserializes events and sorts them by date, as `Loan::with_events` does
+#[cfg(feature = "serde")]
+pub(crate) fn deserialize_sorted<'de, D>(deserializer: D) -> Result<Vec<LoanEvent>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut events = <Vec<LoanEvent> as serde::Deserialize>::deserialize(deserializer)?;
+    events.sort_by_key(|event| event.date);
+    Ok(events)
+}
+
+impl Display for LoanEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            LoanEventKind::Prepayment(amount) => {
+                f.write_fmt(format_args!("Prepayment {:.2}", amount))
+            }
+            LoanEventKind::EarlyRepayment => f.write_str("Early Repayment"),
+            LoanEventKind::Drawdown(amount) => f.write_fmt(format_args!("Drawdown {:.2}", amount)),
+        }
+    }
+}
+
+/// Error reading an events file, with the line it occurred on
+#[derive(Debug)]
+pub struct LoanEventError {
+    line: u64,
+    message: String,
+}
+
+impl LoanEventError {
+    fn new(line: u64, message: String) -> Self {
+        Self { line, message }
+    }
+
+    /// Line of the file the error occurred on, 0 when it is not tied to a line
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl Display for LoanEventError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error reading events on line {}: {}",
+            self.line, self.message
+        ))
+    }
+}
+
+impl Error for LoanEventError {}
+
+impl LoanEvent {
+    /// Reads events from a CSV file with `date,event,amount` columns, where
+    /// event is one of prepayment, repayment or drawdown. Full repayments
+    /// leave the amount empty.
+    pub fn from_csv(path: &Path) -> Result<Vec<Self>, LoanEventError> {
+        let mut reader =
+            csv::Reader::from_path(path).map_err(|e| LoanEventError::new(0, e.to_string()))?;
+        let mut events = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| {
+                let line = e.position().map_or(0, |position| position.line());
+                LoanEventError::new(line, e.to_string())
+            })?;
+            let line = record.position().map_or(0, |position| position.line());
+            let field = |index: usize| record.get(index).map(str::trim).unwrap_or_default();
+            let amount = || {
+                Decimal::from_str(field(2)).map_err(|e| {
+                    LoanEventError::new(line, format!("invalid amount {:?}: {}", field(2), e))
+                })
+            };
+
+            let date = NaiveDate::parse_from_str(field(0), "%Y-%m-%d")
+                .map_err(|e| LoanEventError::new(line, e.to_string()))?;
+            let kind = match field(1).to_lowercase().as_str() {
+                "prepayment" => LoanEventKind::Prepayment(amount()?),
+                "repayment" => LoanEventKind::EarlyRepayment,
+                "drawdown" => LoanEventKind::Drawdown(amount()?),
+                event => {
+                    return Err(LoanEventError::new(
+                        line,
+                        format!("unknown event {:?}", event),
+                    ))
+                }
+            };
+            if let LoanEventKind::Prepayment(amount) | LoanEventKind::Drawdown(amount) = kind {
+                if amount <= Decimal::ZERO {
+                    return Err(LoanEventError::new(
+                        line,
+                        format!("amount must be positive, got {}", amount),
+                    ));
+                }
+            }
+            events.push(LoanEvent { date, kind });
+        }
+        Ok(events)
+    }
+
+    /// The outstanding principal after the event
+    pub fn apply(&self, balance: Decimal) -> Decimal {
+        match self.kind {
+            LoanEventKind::Prepayment(amount) => (balance - amount).max(Decimal::ZERO),
+            LoanEventKind::EarlyRepayment => Decimal::ZERO,
+            LoanEventKind::Drawdown(amount) => balance + amount,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::currency::CurrencyCode;
+    use crate::day_count::DayCountConvention;
+    use crate::loan::{Loan, Schedule};
+    use crate::rate_schedule::RateSchedule;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Writes `contents` to a file in the temporary directory, named after
+    // the process so that concurrent test runs do not share files
+    fn events_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oneiro-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn event(date: NaiveDate, kind: LoanEventKind) -> LoanEvent {
+        LoanEvent { date, kind }
+    }
+
+    #[test]
+    fn events_change_the_balance() {
+        let balance = Decimal::from(1_000);
+        let prepayment = event(
+            date(2023, 1, 1),
+            LoanEventKind::Prepayment(Decimal::from(250)),
+        );
+        assert_eq!(prepayment.apply(balance), Decimal::from(750));
+        let drawdown = event(
+            date(2023, 1, 1),
+            LoanEventKind::Drawdown(Decimal::from(500)),
+        );
+        assert_eq!(drawdown.apply(balance), Decimal::from(1_500));
+        let repayment = event(date(2023, 1, 1), LoanEventKind::EarlyRepayment);
+        assert_eq!(repayment.apply(balance), Decimal::ZERO);
+    }
+
+    #[test]
+    fn prepayments_above_the_balance_repay_it() {
+        let prepayment = event(
+            date(2023, 1, 1),
+            LoanEventKind::Prepayment(Decimal::from(1_500)),
+        );
+        assert_eq!(prepayment.apply(Decimal::from(1_000)), Decimal::ZERO);
+    }
+
+    #[test]
+    fn balances_change_from_the_start_of_the_event_date() {
+        // 3.65% ACT/365F on 100,000 accrues 10 a day
+        let loan = Loan::new(
+            date(2023, 1, 1),
+            date(2023, 4, 30),
+            Decimal::from(100_000),
+            RateSchedule::flat(Decimal::new(365, 2)),
+            Decimal::ZERO,
+            CurrencyCode::GBP,
+        )
+        .with_day_count(DayCountConvention::Act365Fixed)
+        .with_events(vec![
+            event(date(2023, 4, 1), LoanEventKind::EarlyRepayment),
+            event(
+                date(2023, 2, 1),
+                LoanEventKind::Prepayment(Decimal::from(25_000)),
+            ),
+            event(
+                date(2023, 3, 1),
+                LoanEventKind::Drawdown(Decimal::from(50_000)),
+            ),
+        ]);
+        let schedule = Schedule::new(&loan).unwrap();
+        let day = |date| {
+            let entry = schedule.entry_on(date).unwrap();
+            (
+                entry.opening_balance.value,
+                entry.daily_interest_with_margin.value,
+                entry.events.len(),
+            )
+        };
+        assert_eq!(
+            day(date(2023, 1, 31)),
+            (Decimal::from(100_000), Decimal::from(10), 0)
+        );
+        assert_eq!(
+            day(date(2023, 2, 1)),
+            (Decimal::from(75_000), Decimal::new(75, 1), 1)
+        );
+        assert_eq!(
+            day(date(2023, 3, 1)),
+            (Decimal::from(125_000), Decimal::new(125, 1), 1)
+        );
+        assert_eq!(day(date(2023, 4, 1)), (Decimal::ZERO, Decimal::ZERO, 1));
+        assert_eq!(day(date(2023, 4, 30)), (Decimal::ZERO, Decimal::ZERO, 0));
+        // 31 days at 10, 28 at 7.50 and 31 at 12.50
+        assert_eq!(
+            loan.total_interest().unwrap().unwrap().with_margin.value,
+            Decimal::new(90_750, 2)
+        );
+    }
+
+    #[test]
+    fn csv_events_are_read_with_their_kind() {
+        let path = events_file(
+            "events.csv",
+            "date,event,amount\n2023-06-01,Prepayment, 250.00\n2023-09-01,drawdown,500\n2023-11-01,repayment,\n",
+        );
+        assert_eq!(
+            LoanEvent::from_csv(&path).unwrap(),
+            [
+                event(
+                    date(2023, 6, 1),
+                    LoanEventKind::Prepayment(Decimal::new(25_000, 2))
+                ),
+                event(
+                    date(2023, 9, 1),
+                    LoanEventKind::Drawdown(Decimal::from(500))
+                ),
+                event(date(2023, 11, 1), LoanEventKind::EarlyRepayment),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_errors_name_their_line() {
+        let error = |name, contents| {
+            LoanEvent::from_csv(&events_file(name, contents))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            error(
+                "unknown.csv",
+                "date,event,amount\n2023-06-01,prepayment,1\n2023-07-01,refund,1\n"
+            ),
+            "Error reading events on line 3: unknown event \"refund\""
+        );
+        assert_eq!(
+            error(
+                "negative.csv",
+                "date,event,amount\n2023-06-01,drawdown,-5\n"
+            ),
+            "Error reading events on line 2: amount must be positive, got -5"
+        );
+        assert!(
+            error("missing.csv", "date,event,amount\n2023-06-01,prepayment,\n")
+                .starts_with("Error reading events on line 2: invalid amount \"\"")
+        );
+    }
+}
@@ -0,0 +1,261 @@
+use std::{error::Error, fmt::Display, str::FromStr};
+
+use rust_decimal::Decimal;
+
+/// Fee charged on the whole drawn balance while utilisation is above a
+/// percentage of the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UtilisationTier {
+    /// Utilisation, as a percentage of the limit, the fee applies above
+    pub above_percentage: Decimal,
+    /// Annual fee rate as a percentage
+    pub fee_rate: Decimal,
+}
+
+/// Utilisation tier that is not in the `above_percentage:fee_rate` form
+#[derive(Debug)]
+pub struct InvalidUtilisationTierError {
+    tier: String,
+}
+
+impl InvalidUtilisationTierError {
+    fn new(tier: String) -> Self {
+        Self { tier }
+    }
+}
+
+impl Display for InvalidUtilisationTierError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error invalid utilisation tier, expected <above percentage>:<fee rate>: {}",
+            self.tier
+        ))
+    }
+}
+
+impl Error for InvalidUtilisationTierError {}
+
+impl TryFrom<&str> for UtilisationTier {
+    type Error = InvalidUtilisationTierError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (above_percentage, fee_rate) = value
+            .split_once(':')
+            .ok_or_else(|| InvalidUtilisationTierError::new(value.into()))?;
+        let parse = |field: &str| {
+            Decimal::from_str(field.trim())
+                .map_err(|_| InvalidUtilisationTierError::new(value.into()))
+        };
+        Ok(UtilisationTier {
+            above_percentage: parse(above_percentage)?,
+            fee_rate: parse(fee_rate)?,
+        })
+    }
+}
+
+/// Fee charged on the undrawn part of a revolving facility
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CommitmentFee {
+    /// Annual fee rate as a percentage
+    Rate(Decimal),
+    /// Annual fee rate as a percentage of the margin
+    MarginPercentage(Decimal),
+}
+
+/// Revolving credit facility that can be drawn up to its limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RevolvingFacility {
+    /// Committed limit the drawn balance may not exceed
+    pub limit: Decimal,
+    /// Fee charged on the undrawn part of the limit
+    pub commitment_fee: CommitmentFee,
+    /// Fees charged on the drawn balance, by utilisation of the limit
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_sorted"))]
+    pub utilisation_tiers: Vec<UtilisationTier>,
+}
+
+/// Deserializes utilisation tiers and sorts them by threshold, as
+/// `RevolvingFacility::new` does
+#[cfg(feature = "serde")]
+fn deserialize_sorted<'de, D>(deserializer: D) -> Result<Vec<UtilisationTier>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let mut tiers = <Vec<UtilisationTier> as serde::Deserialize>::deserialize(deserializer)?;
+    tiers.sort_by_key(|tier| tier.above_percentage);
+    Ok(tiers)
+}
+
+impl RevolvingFacility {
+    /// A facility with `limit`, sorting the utilisation tiers by threshold
+    pub fn new(
+        limit: Decimal,
+        commitment_fee: CommitmentFee,
+        mut utilisation_tiers: Vec<UtilisationTier>,
+    ) -> Self {
+        utilisation_tiers.sort_by_key(|tier| tier.above_percentage);
+        Self {
+            limit,
+            commitment_fee,
+            utilisation_tiers,
+        }
+    }
+
+    /// Part of the limit not drawn, never negative
+    pub fn undrawn(&self, drawn: Decimal) -> Decimal {
+        (self.limit - drawn).max(Decimal::ZERO)
+    }
+
+    /// Annual commitment fee rate as a percentage
+    pub fn commitment_fee_rate(&self, margin: Decimal) -> Decimal {
+        match self.commitment_fee {
+            CommitmentFee::Rate(rate) => rate,
+            CommitmentFee::MarginPercentage(percentage) => margin * percentage / Decimal::from(100),
+        }
+    }
+
+    /// Annual utilisation fee rate as a percentage, from the highest tier the
+    /// drawn balance is above
+    pub fn utilisation_fee_rate(&self, drawn: Decimal) -> Decimal {
+        if self.limit.is_zero() {
+            return Decimal::ZERO;
+        }
+        let utilisation = drawn / self.limit * Decimal::from(100);
+        self.utilisation_tiers
+            .iter()
+            .filter(|tier| utilisation > tier.above_percentage)
+            .max_by_key(|tier| tier.above_percentage)
+            .map_or(Decimal::ZERO, |tier| tier.fee_rate)
+    }
+}
+
+/// Whether a loan is a term loan or a revolving credit facility
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum FacilityType {
+    /// Loan drawn in full at the start
+    #[default]
+    Term,
+    /// Revolving credit facility with commitment and utilisation fees
+    Revolving(RevolvingFacility),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tier(above_percentage: i64, fee_rate: Decimal) -> UtilisationTier {
+        UtilisationTier {
+            above_percentage: Decimal::from(above_percentage),
+            fee_rate,
+        }
+    }
+
+    fn facility(utilisation_tiers: Vec<UtilisationTier>) -> RevolvingFacility {
+        RevolvingFacility::new(
+            Decimal::from(1_000_000),
+            CommitmentFee::MarginPercentage(Decimal::from(35)),
+            utilisation_tiers,
+        )
+    }
+
+    #[test]
+    fn commitment_fees_are_a_rate_or_a_percentage_of_the_margin() {
+        let margin = Decimal::new(25, 1);
+        assert_eq!(
+            facility(Vec::new()).commitment_fee_rate(margin),
+            Decimal::new(875, 3)
+        );
+        let fixed = RevolvingFacility::new(
+            Decimal::from(1_000_000),
+            CommitmentFee::Rate(Decimal::new(4, 1)),
+            Vec::new(),
+        );
+        assert_eq!(fixed.commitment_fee_rate(margin), Decimal::new(4, 1));
+    }
+
+    #[test]
+    fn undrawn_balances_are_never_negative() {
+        let facility = facility(Vec::new());
+        assert_eq!(
+            facility.undrawn(Decimal::from(400_000)),
+            Decimal::from(600_000)
+        );
+        assert_eq!(facility.undrawn(Decimal::from(1_200_000)), Decimal::ZERO);
+    }
+
+    #[test]
+    fn utilisation_fees_come_from_the_highest_tier_exceeded() {
+        let facility = facility(vec![
+            tier(33, Decimal::new(1, 1)),
+            tier(66, Decimal::new(2, 1)),
+        ]);
+        let fee = |drawn| facility.utilisation_fee_rate(Decimal::from(drawn));
+        assert_eq!(fee(330_000), Decimal::ZERO);
+        assert_eq!(fee(330_001), Decimal::new(1, 1));
+        assert_eq!(fee(660_000), Decimal::new(1, 1));
+        assert_eq!(fee(900_000), Decimal::new(2, 1));
+    }
+
+    #[test]
+    fn utilisation_tiers_may_be_given_in_any_order() {
+        let tiers = vec![tier(66, Decimal::new(2, 1)), tier(33, Decimal::new(1, 1))];
+        let sorted = facility(tiers.clone());
+        assert_eq!(
+            sorted.utilisation_tiers[0].above_percentage,
+            Decimal::from(33)
+        );
+        assert_eq!(
+            sorted.utilisation_fee_rate(Decimal::from(900_000)),
+            Decimal::new(2, 1)
+        );
+
+        let unsorted = RevolvingFacility {
+            utilisation_tiers: tiers,
+            ..sorted
+        };
+        assert_eq!(
+            unsorted.utilisation_fee_rate(Decimal::from(900_000)),
+            Decimal::new(2, 1)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn utilisation_tiers_are_read_in_threshold_order() {
+        let facility: RevolvingFacility = serde_json::from_str(
+            r#"{
+                "limit": "1000000",
+                "commitment_fee": {"rate": "0.4"},
+                "utilisation_tiers": [
+                    {"above_percentage": "66", "fee_rate": "0.2"},
+                    {"above_percentage": "33", "fee_rate": "0.1"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            facility.utilisation_tiers,
+            vec![tier(33, Decimal::new(1, 1)), tier(66, Decimal::new(2, 1))]
+        );
+    }
+
+    #[test]
+    fn utilisation_tiers_are_parsed_from_threshold_and_rate() {
+        assert_eq!(
+            UtilisationTier::try_from("50: 0.15").unwrap(),
+            tier(50, Decimal::new(15, 2))
+        );
+        assert!(UtilisationTier::try_from("50").is_err());
+        assert!(UtilisationTier::try_from("fifty:0.15").is_err());
+    }
+}
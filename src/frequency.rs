@@ -0,0 +1,78 @@
+use std::{error::Error, fmt::Display};
+
+/// How often a regular event, such as a payment, recurs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Frequency {
+    /// Every month
+    Monthly,
+    /// Every three months
+    Quarterly,
+    /// Every six months
+    SemiAnnual,
+    /// Once a year
+    Annual,
+}
+
+impl Display for Frequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Frequency::Monthly => f.write_str("monthly"),
+            Frequency::Quarterly => f.write_str("quarterly"),
+            Frequency::SemiAnnual => f.write_str("semi-annual"),
+            Frequency::Annual => f.write_str("annual"),
+        }
+    }
+}
+
+/// Payment frequency that is not recognised
+#[derive(Debug)]
+pub struct UnknownFrequencyError {
+    frequency: String,
+}
+
+impl UnknownFrequencyError {
+    fn new(frequency: String) -> Self {
+        Self { frequency }
+    }
+}
+
+impl Display for UnknownFrequencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Error unknown frequency: {}", self.frequency))
+    }
+}
+
+impl Error for UnknownFrequencyError {}
+
+impl TryFrom<&str> for Frequency {
+    type Error = UnknownFrequencyError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "monthly" => Ok(Frequency::Monthly),
+            "quarterly" => Ok(Frequency::Quarterly),
+            "semi-annual" | "semiannual" | "semi-annually" => Ok(Frequency::SemiAnnual),
+            "annual" | "annually" => Ok(Frequency::Annual),
+            _ => Err(UnknownFrequencyError::new(value.into())),
+        }
+    }
+}
+
+impl Frequency {
+    /// Number of months in each period
+    pub fn months(&self) -> u32 {
+        match self {
+            Frequency::Monthly => 1,
+            Frequency::Quarterly => 3,
+            Frequency::SemiAnnual => 6,
+            Frequency::Annual => 12,
+        }
+    }
+
+    /// Number of periods in a year
+    pub fn periods_per_year(&self) -> u32 {
+        12 / self.months()
+    }
+}
@@ -0,0 +1,281 @@
+use std::{collections::HashMap, error::Error, fmt::Display, path::Path, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::currency::CurrencyCode;
+use crate::money::Money;
+
+/// Daily exchange rates, each quoted as units of the quote currency for one
+/// unit of the base currency and applying until the next rate of the pair
+#[derive(Debug, Clone, Default)]
+pub struct FxRates {
+    /// Rates of each `(base, quote)` pair, kept sorted by date
+    rates: HashMap<(CurrencyCode, CurrencyCode), Vec<(NaiveDate, Decimal)>>,
+}
+
+/// Error reading an FX rates file, with the line it occurred on
+#[derive(Debug)]
+pub struct FxRatesError {
+    line: u64,
+    message: String,
+}
+
+impl FxRatesError {
+    fn new(line: u64, message: String) -> Self {
+        Self { line, message }
+    }
+
+    /// Line of the file the error occurred on, 0 when it is not tied to a line
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl Display for FxRatesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error reading FX rates on line {}: {}",
+            self.line, self.message
+        ))
+    }
+}
+
+impl Error for FxRatesError {}
+
+/// No rate converts between two currencies on a date
+#[derive(Debug)]
+pub struct MissingFxRateError {
+    from: CurrencyCode,
+    to: CurrencyCode,
+    date: NaiveDate,
+}
+
+impl MissingFxRateError {
+    fn new(from: CurrencyCode, to: CurrencyCode, date: NaiveDate) -> Self {
+        Self { from, to, date }
+    }
+}
+
+impl Display for MissingFxRateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error no FX rate from {} to {} on or before {}",
+            self.from, self.to, self.date
+        ))
+    }
+}
+
+impl Error for MissingFxRateError {}
+
+// Splits a pair written as EURGBP, EUR/GBP or EUR-GBP into its currencies
+fn parse_pair(pair: &str) -> Result<(CurrencyCode, CurrencyCode), String> {
+    let pair = pair.replace(['/', '-'], "");
+    if pair.len() != 6 || !pair.is_ascii() {
+        return Err(format!("invalid currency pair: {}", pair));
+    }
+    let (base, quote) = pair.split_at(3);
+    let base = CurrencyCode::try_from(base).map_err(|e| e.to_string())?;
+    let quote = CurrencyCode::try_from(quote).map_err(|e| e.to_string())?;
+    Ok((base, quote))
+}
+
+impl FxRates {
+    /// Reads rates from a CSV file with `date,pair,rate` columns, dates
+    /// formatted as YYYY-MM-DD and pairs as base then quote, e.g. EURGBP
+    pub fn from_csv(path: &Path) -> Result<Self, FxRatesError> {
+        let mut reader =
+            csv::Reader::from_path(path).map_err(|e| FxRatesError::new(0, e.to_string()))?;
+        let mut fx_rates = FxRates::default();
+        for record in reader.records() {
+            let record = record.map_err(|e| {
+                let line = e.position().map_or(0, |position| position.line());
+                FxRatesError::new(line, e.to_string())
+            })?;
+            let line = record.position().map_or(0, |position| position.line());
+            let field = |index: usize, name: &str| {
+                record
+                    .get(index)
+                    .map(str::trim)
+                    .ok_or_else(|| FxRatesError::new(line, format!("missing {}", name)))
+            };
+            let date = NaiveDate::parse_from_str(field(0, "date")?, "%Y-%m-%d")
+                .map_err(|e| FxRatesError::new(line, e.to_string()))?;
+            let pair = parse_pair(field(1, "pair")?).map_err(|e| FxRatesError::new(line, e))?;
+            let rate = Decimal::from_str(field(2, "rate")?)
+                .map_err(|e| FxRatesError::new(line, e.to_string()))?;
+            if rate <= Decimal::ZERO {
+                return Err(FxRatesError::new(line, "rate must be positive".into()));
+            }
+            fx_rates.rates.entry(pair).or_default().push((date, rate));
+        }
+        if fx_rates.rates.is_empty() {
+            return Err(FxRatesError::new(0, "no rates found".into()));
+        }
+        fx_rates
+            .rates
+            .values_mut()
+            .for_each(|rates| rates.sort_by_key(|(date, _)| *date));
+        Ok(fx_rates)
+    }
+
+    // Latest rate of the pair on or before `date`
+    fn quoted_rate(
+        &self,
+        base: CurrencyCode,
+        quote: CurrencyCode,
+        date: NaiveDate,
+    ) -> Option<Decimal> {
+        let rates = self.rates.get(&(base, quote))?;
+        let index = rates.partition_point(|(effective_date, _)| *effective_date <= date);
+        index.checked_sub(1).map(|index| rates[index].1)
+    }
+
+    /// Units of `to` for one unit of `from` on `date`, from the pair quoted
+    /// either way round
+    pub fn rate_on(
+        &self,
+        from: CurrencyCode,
+        to: CurrencyCode,
+        date: NaiveDate,
+    ) -> Option<Decimal> {
+        if from == to {
+            return Some(Decimal::ONE);
+        }
+        self.quoted_rate(from, to, date).or_else(|| {
+            self.quoted_rate(to, from, date)
+                .map(|rate| Decimal::ONE / rate)
+        })
+    }
+
+    /// `money` in the currency `to` at the rate on `date`
+    pub fn convert(
+        &self,
+        money: Money,
+        to: CurrencyCode,
+        date: NaiveDate,
+    ) -> Result<Money, MissingFxRateError> {
+        let rate = self
+            .rate_on(money.code, to, date)
+            .ok_or_else(|| MissingFxRateError::new(money.code, to, date))?;
+        Ok(Money::new(money.value * rate, to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Writes `contents` to a file in the temporary directory, named after
+    // the process so that concurrent test runs do not share files
+    fn rates_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oneiro-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn eur_gbp() -> FxRates {
+        FxRates::from_csv(&rates_file(
+            "eurgbp.csv",
+            "date,pair,rate\n2024-01-03,EUR/GBP,0.80\n2024-01-01,EURGBP,0.85\n2024-01-02,usd-eur,0.90\n",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn rates_are_the_latest_on_or_before_the_date() {
+        let fx_rates = eur_gbp();
+        let rate = |date| fx_rates.rate_on(CurrencyCode::EUR, CurrencyCode::GBP, date);
+        assert_eq!(rate(date(2023, 12, 31)), None);
+        assert_eq!(rate(date(2024, 1, 1)), Some(Decimal::new(85, 2)));
+        assert_eq!(rate(date(2024, 1, 2)), Some(Decimal::new(85, 2)));
+        assert_eq!(rate(date(2024, 1, 3)), Some(Decimal::new(80, 2)));
+        assert_eq!(rate(date(2025, 1, 1)), Some(Decimal::new(80, 2)));
+    }
+
+    #[test]
+    fn pairs_convert_either_way_round() {
+        let fx_rates = eur_gbp();
+        assert_eq!(
+            fx_rates.rate_on(CurrencyCode::GBP, CurrencyCode::EUR, date(2024, 1, 3)),
+            Some(Decimal::new(125, 2))
+        );
+        assert_eq!(
+            fx_rates.rate_on(CurrencyCode::EUR, CurrencyCode::USD, date(2024, 1, 2)),
+            Some(Decimal::ONE / Decimal::new(90, 2))
+        );
+        assert_eq!(
+            fx_rates.rate_on(CurrencyCode::JPY, CurrencyCode::JPY, date(2000, 1, 1)),
+            Some(Decimal::ONE)
+        );
+    }
+
+    #[test]
+    fn amounts_convert_at_the_rate_on_their_date() {
+        let fx_rates = eur_gbp();
+        let eur = Money::new(Decimal::from(1_000), CurrencyCode::EUR);
+        assert_eq!(
+            fx_rates
+                .convert(eur, CurrencyCode::GBP, date(2024, 1, 2))
+                .unwrap(),
+            Money::new(Decimal::from(850), CurrencyCode::GBP)
+        );
+        let gbp = Money::new(Decimal::from(800), CurrencyCode::GBP);
+        assert_eq!(
+            fx_rates
+                .convert(gbp, CurrencyCode::EUR, date(2024, 1, 3))
+                .unwrap(),
+            Money::new(Decimal::from(1_000), CurrencyCode::EUR)
+        );
+    }
+
+    #[test]
+    fn missing_rates_are_errors() {
+        let fx_rates = eur_gbp();
+        let eur = Money::new(Decimal::from(1_000), CurrencyCode::EUR);
+        assert_eq!(
+            fx_rates
+                .convert(eur, CurrencyCode::GBP, date(2023, 12, 31))
+                .unwrap_err()
+                .to_string(),
+            "Error no FX rate from EUR to GBP on or before 2023-12-31"
+        );
+        // Rates are not chained through a third currency
+        let usd = Money::new(Decimal::from(1_000), CurrencyCode::USD);
+        assert!(fx_rates
+            .convert(usd, CurrencyCode::GBP, date(2024, 1, 3))
+            .is_err());
+    }
+
+    #[test]
+    fn csv_errors_name_their_line() {
+        let error = |name, contents| FxRates::from_csv(&rates_file(name, contents)).unwrap_err();
+        let pair = error(
+            "bad-pair.csv",
+            "date,pair,rate\n2024-01-01,EURGBP,0.85\n2024-01-02,EURO,0.85\n",
+        );
+        assert_eq!(pair.line(), 3);
+        assert_eq!(
+            pair.to_string(),
+            "Error reading FX rates on line 3: invalid currency pair: EURO"
+        );
+        assert_eq!(
+            error("zero.csv", "date,pair,rate\n2024-01-01,EURGBP,0\n").to_string(),
+            "Error reading FX rates on line 2: rate must be positive"
+        );
+        assert_eq!(
+            error("unknown.csv", "date,pair,rate\n2024-01-01,EURABC,1\n").to_string(),
+            "Error reading FX rates on line 2: Error unknown currency code: ABC"
+        );
+        assert_eq!(
+            error("empty.csv", "date,pair,rate\n").to_string(),
+            "Error reading FX rates on line 0: no rates found"
+        );
+    }
+}
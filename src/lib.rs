@@ -0,0 +1,109 @@
+//! Daily interest accrual engine for term loans and revolving credit
+//! facilities.
+//!
+//! Build a [`Loan`] with [`Loan::try_new`] and the `with_*` methods, then
+//! calculate its [`Schedule`] of daily [`Entry`]s and [`Payment`]s:
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use oneiro::{CurrencyCode, Loan, RateSchedule, Schedule};
+//! use rust_decimal::Decimal;
+//!
+//! let loan = Loan::try_new(
+//!     NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+//!     NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+//!     Decimal::from(1_000_000),
+//!     RateSchedule::flat(Decimal::from(5)),
+//!     Decimal::from(2),
+//!     CurrencyCode::GBP,
+//! )
+//! .unwrap();
+//! let schedule = Schedule::new(&loan).unwrap();
+//! let total_interest = schedule.calculate_interest().unwrap().unwrap();
+//! assert_eq!(total_interest.with_margin.code, CurrencyCode::GBP);
+//! ```
+//!
+//! # Features
+//!
+//! - `serde` derives `Serialize` and `Deserialize` for loans, schedules and
+//!   their parts. Decimals are written as strings so no precision is lost,
+//!   dates as ISO 8601 and [`Money`] as an `amount` and ISO 4217 `currency`.
+//!   Terms left out of a loan take the same defaults as [`Loan::new`].
+//! - `schema` adds a JSON Schema of the loan input, see
+//!   `examples/loan_schema.rs` and `schema/loan.schema.json`.
+//! - `loan-file`, on by default, adds `Loan::from_file` to read a loan from a
+//!   TOML, YAML or JSON file. It enables `serde` and is needed by the CLI.
+//! - `portfolio`, on by default, adds the [`portfolio`] module to read many
+//!   loans from one file and calculate them across threads. It enables
+//!   `serde` and is needed by the CLI.
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! # {
+//! use oneiro::{day_count::DayCountConvention, Loan};
+//!
+//! let loan: Loan = serde_json::from_str(
+//!     r#"{
+//!         "start_date": "2024-01-01",
+//!         "end_date": "2024-12-31",
+//!         "loan_amount": "1000000",
+//!         "base_rate": [{ "effective_date": "2024-01-01", "rate": "5" }],
+//!         "margin": "2",
+//!         "currency": "GBP"
+//!     }"#,
+//! )
+//! .unwrap();
+//! assert_eq!(loan.day_count, DayCountConvention::default());
+//! let json = serde_json::to_string(&loan).unwrap();
+//! let round_trip: Loan = serde_json::from_str(&json).unwrap();
+//! assert_eq!(serde_json::to_string(&round_trip).unwrap(), json);
+//! # }
+//! ```
+
+#![warn(missing_docs)]
+
+/// Holiday calendars and business day conventions
+pub mod calendar;
+/// How accrued interest is capitalised
+pub mod compounding;
+/// ISO 4217 currency codes
+pub mod currency;
+/// Day count conventions for year fractions
+pub mod day_count;
+/// Prepayments, early repayment and drawdowns
+pub mod event;
+/// Term loans and revolving credit facilities
+pub mod facility;
+/// Payment frequencies
+pub mod frequency;
+/// Exchange rates for reporting in another currency
+pub mod fx;
+/// Loan terms and the daily accrual schedule
+pub mod loan;
+/// Amounts in a currency and their arithmetic
+pub mod money;
+/// Terms replacing those of a loan, such as flags over a loan file
+pub mod overrides;
+/// Interest periods, stubs and roll rules
+pub mod period;
+/// Many loans read from one file and calculated together
+#[cfg(feature = "portfolio")]
+pub mod portfolio;
+/// Base rate floors, caps and collars
+pub mod rate_bounds;
+/// Base rate fixings
+pub mod rate_schedule;
+/// Repayment profiles
+pub mod repayment;
+/// Risk free rates compounded in arrears
+pub mod rfr;
+/// Rounding policies for interest and fees
+pub mod rounding;
+
+pub use currency::CurrencyCode;
+#[cfg(feature = "loan-file")]
+pub use loan::LoanFileError;
+pub use loan::{Accruals, Entry, Loan, LoanError, Payment, RateLimits, Schedule, TotalInterest};
+pub use money::{CurrencyMismatchError, Money};
+pub use overrides::{LoanOverrides, OverrideError};
+pub use rate_schedule::RateSchedule;
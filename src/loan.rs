@@ -1,165 +1,494 @@
-use std::{error::Error, fmt::Display, ops::Add};
+use std::{error::Error, fmt::Display};
+#[cfg(feature = "loan-file")]
+use std::{fs, path::Path};
 
 use chrono::{Duration, NaiveDate};
+use log::{debug, trace};
 use rust_decimal::prelude::{Decimal, Zero};
 
-const DAYS_IN_YEAR: u64 = 365;
+use crate::calendar::{BusinessDayConvention, HolidayCalendar};
+use crate::compounding::CompoundingMethod;
+use crate::currency::CurrencyCode;
+#[cfg(feature = "loan-file")]
+use crate::currency::UnknownCurrencyError;
+use crate::day_count::{DayCountConvention, ReferencePeriod};
+use crate::event::LoanEvent;
+use crate::facility::FacilityType;
+use crate::frequency::Frequency;
+use crate::fx::{FxRates, MissingFxRateError};
+use crate::money::{CurrencyMismatchError, Money};
+use crate::overrides::{LoanOverrides, OverrideError};
+use crate::period::{Period, PeriodRules};
+use crate::rate_bounds::{FloorBasis, RateBounds};
+use crate::rate_schedule::RateSchedule;
+use crate::repayment::RepaymentProfile;
+use crate::rfr::{CompoundedRate, RfrError, RfrTerms};
+use crate::rounding::{RoundingDifference, RoundingPolicy, RoundingStage};
 
-#[derive(Debug, Clone, Copy)]
-pub enum CurrencyCode {
-    GBP,
-    EUR,
-    USD,
+/// Accrual of one calendar day of the loan
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entry {
+    /// Interest accrued on the day at the base rate alone
+    pub daily_interest_without_margin: Money,
+    /// Interest accrued on the day at the base rate plus margin
+    pub daily_interest_with_margin: Money,
+    /// Day the interest accrues on
+    pub accrual_date: NaiveDate,
+    /// Days since the start date
+    pub days_elapsed: u64,
+    /// Length of the year the accrual date falls in under the day count
+    pub days_in_year: u32,
+    /// Base rate fixing observed for the accrual date
+    pub raw_base_rate: Decimal,
+    /// Base rate that applied after any floor and cap
+    pub base_rate: Decimal,
+    /// Prepayments and drawdowns that took effect at the start of the day
+    pub events: Vec<LoanEvent>,
+    /// Interest-bearing balance at the start of the day, after any events
+    pub opening_balance: Money,
+    /// Undrawn part of a revolving facility's limit
+    pub undrawn_balance: Money,
+    /// Commitment fee accrued on the undrawn balance
+    pub commitment_fee: Money,
+    /// Utilisation fee accrued on the drawn balance
+    pub utilisation_fee: Money,
+    /// Interest accrued but not yet capitalised at the end of the day
+    pub accrued_interest: Money,
+    /// Interest added to the balance at the end of the day
+    pub capitalised_interest: Money,
+    /// RFR compounded in arrears rates, for loans with RFR terms
+    pub compounded_rate: Option<CompoundedRate>,
+    /// Payment made at the end of the day, if it is a payment date
+    pub payment: Option<Payment>,
 }
 
-impl Display for CurrencyCode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            CurrencyCode::GBP => f.write_str("GBP"),
-            CurrencyCode::EUR => f.write_str("EUR"),
-            CurrencyCode::USD => f.write_str("USD"),
-        }
+impl Entry {
+    /// The entry with its amounts converted into `currency` at the rate on
+    /// the accrual date
+    pub fn convert(
+        &self,
+        fx_rates: &FxRates,
+        currency: CurrencyCode,
+    ) -> Result<Entry, MissingFxRateError> {
+        let convert = |money| fx_rates.convert(money, currency, self.accrual_date);
+        Ok(Entry {
+            daily_interest_without_margin: convert(self.daily_interest_without_margin)?,
+            daily_interest_with_margin: convert(self.daily_interest_with_margin)?,
+            accrual_date: self.accrual_date,
+            days_elapsed: self.days_elapsed,
+            days_in_year: self.days_in_year,
+            raw_base_rate: self.raw_base_rate,
+            base_rate: self.base_rate,
+            events: self.events.clone(),
+            opening_balance: convert(self.opening_balance)?,
+            undrawn_balance: convert(self.undrawn_balance)?,
+            commitment_fee: convert(self.commitment_fee)?,
+            utilisation_fee: convert(self.utilisation_fee)?,
+            accrued_interest: convert(self.accrued_interest)?,
+            capitalised_interest: convert(self.capitalised_interest)?,
+            compounded_rate: self.compounded_rate,
+            payment: self
+                .payment
+                .map(|payment| payment.convert(fx_rates, currency, self.accrual_date))
+                .transpose()?,
+        })
     }
 }
 
-impl CurrencyCode {
-    fn symbol(&self) -> &str {
-        match self {
-            CurrencyCode::GBP => "£",
-            CurrencyCode::EUR => "€",
-            CurrencyCode::USD => "$",
-        }
+/// Principal, interest and fees paid at the end of a payment period
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Payment {
+    /// End of the payment period the payment settles
+    pub period_end: NaiveDate,
+    /// Business day the payment is made on
+    pub payment_date: NaiveDate,
+    /// Principal repaid
+    pub principal: Money,
+    /// Interest paid, rounded as the loan's rounding policy requires
+    pub interest: Money,
+    /// Commitment and utilisation fees of a revolving facility
+    pub fees: Money,
+    /// Principal still outstanding after the payment
+    pub outstanding_principal: Money,
+}
+
+impl Payment {
+    /// The payment with its amounts converted into `currency` at the rate on
+    /// `date`
+    pub fn convert(
+        &self,
+        fx_rates: &FxRates,
+        currency: CurrencyCode,
+        date: NaiveDate,
+    ) -> Result<Payment, MissingFxRateError> {
+        let convert = |money| fx_rates.convert(money, currency, date);
+        Ok(Payment {
+            period_end: self.period_end,
+            payment_date: self.payment_date,
+            principal: convert(self.principal)?,
+            interest: convert(self.interest)?,
+            fees: convert(self.fees)?,
+            outstanding_principal: convert(self.outstanding_principal)?,
+        })
+    }
+
+    /// Principal, interest and fees paid together
+    pub fn total(&self) -> Result<Money, CurrencyMismatchError> {
+        Money::sum(
+            self.principal.code,
+            [self.principal, self.interest, self.fees],
+        )
     }
 }
 
+/// Daily accruals of a loan from its start date to its end date
 #[derive(Debug)]
-pub struct UnknownCurrencyError {
-    currency_code: String,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Schedule {
+    /// One entry per calendar day, in date order
+    pub entries: std::vec::Vec<Entry>,
+    /// Rounding applied to payments and totals
+    pub rounding: RoundingPolicy,
 }
 
-impl UnknownCurrencyError {
-    fn new(currency_code: String) -> Self {
-        Self { currency_code }
+/// Entries in date order
+impl IntoIterator for Schedule {
+    type Item = Entry;
+    type IntoIter = std::vec::IntoIter<Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
     }
 }
 
-impl Display for UnknownCurrencyError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "Error unknown currency code: {}",
-            self.currency_code
-        ))
+/// Entries in date order
+impl<'a> IntoIterator for &'a Schedule {
+    type Item = &'a Entry;
+    type IntoIter = std::slice::Iter<'a, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
     }
 }
 
-impl Error for UnknownCurrencyError {}
-
-impl TryFrom<&str> for CurrencyCode {
-    type Error = UnknownCurrencyError;
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match value.to_uppercase().as_str() {
-            "GBP" => Ok(CurrencyCode::GBP),
-            "EUR" => Ok(CurrencyCode::EUR),
-            "USD" => Ok(CurrencyCode::USD),
-            _ => Err(UnknownCurrencyError::new(value.into())),
+impl From<Vec<Entry>> for Schedule {
+    fn from(entries: Vec<Entry>) -> Self {
+        Schedule {
+            entries,
+            rounding: RoundingPolicy::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Money {
-    pub value: Decimal,
-    pub code: CurrencyCode,
+/// Interest and fees accrued over the whole loan
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TotalInterest {
+    /// Interest at the base rate plus margin
+    pub with_margin: Money,
+    /// Interest at the base rate alone
+    pub without_margin: Money,
+    /// Commitment fee of a revolving facility
+    pub commitment_fee: Money,
+    /// Utilisation fee of a revolving facility
+    pub utilisation_fee: Money,
 }
 
-impl Display for Money {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{}{:.2}", self.code.symbol(), self.value))
-    }
+/// Lowest and highest all-in rate, base rate plus margin, a loan may accrue
+/// at, as percentages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RateLimits {
+    /// Lowest all-in rate
+    pub min: Decimal,
+    /// Highest all-in rate
+    pub max: Decimal,
 }
 
-impl Add<Decimal> for Money {
-    type Output = Self;
-    fn add(self, rhs: Decimal) -> Self::Output {
+impl Default for RateLimits {
+    fn default() -> Self {
         Self {
-            value: self.value + rhs,
-            code: self.code,
+            min: Decimal::from(-10),
+            max: Decimal::from(100),
         }
     }
 }
 
-impl Add<Money> for Decimal {
-    type Output = Money;
-    fn add(self, rhs: Money) -> Self::Output {
-        Self::Output {
-            value: self + rhs.value,
-            code: rhs.code,
-        }
-    }
+/// Terms that make a loan impossible to accrue
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoanError {
+    /// The end date is before the start date
+    InvertedDates {
+        /// First day interest would accrue
+        start_date: NaiveDate,
+        /// Last day interest would accrue
+        end_date: NaiveDate,
+    },
+    /// The loan amount is zero or negative
+    NonPositivePrincipal(Decimal),
+    /// A base rate fixing plus the margin is outside the rate limits
+    RateOutOfBounds {
+        /// All-in rate, base rate plus margin
+        rate: Decimal,
+        /// Limits the rate broke
+        limits: RateLimits,
+    },
+    /// The currency has no minor units, e.g. precious metals, funds and
+    /// test codes
+    UnsupportedCurrency(CurrencyCode),
+    /// There is no base rate fixing on or before the start date
+    MissingFixing(NaiveDate),
+    /// The RFR fixings cannot be compounded over one of the interest periods
+    Rfr(RfrError),
+    /// The RFR day basis contradicts the day count, ACT/365F accrues on a
+    /// 365 day year and ACT/360 on a 360 day year
+    RfrDayBasisMismatch {
+        /// Days in the year the fixings are compounded on
+        day_basis: u32,
+        /// Day count the compounded rate accrues under
+        day_count: DayCountConvention,
+    },
+    /// The floor is above the cap, or the floor less the margin is when the
+    /// floor applies to the all-in rate
+    FloorAboveCap {
+        /// Lowest rate, as a percentage
+        floor: Decimal,
+        /// Highest base rate, as a percentage
+        cap: Decimal,
+    },
+    /// The balloon residual is not a percentage between 0 and 100
+    BalloonResidualOutOfRange(Decimal),
+    /// An event falls before the start date or after the end date
+    EventOutsideTerm(LoanEvent),
+    /// The drawn balance of a revolving facility is above its limit
+    AboveFacilityLimit {
+        /// First day the balance is above the limit
+        date: NaiveDate,
+        /// Drawn balance on that day
+        drawn: Decimal,
+        /// Committed limit of the facility
+        limit: Decimal,
+    },
+    /// The roll day is not a day of the month from 1 to 31
+    InvalidRollDay(u32),
 }
 
-// Round a Decimal using Banker's Rounding
-// SEE: https://en.wikipedia.org/wiki/Rounding#Rounding_half_to_even
-fn bankers_round(money: Money) -> Money {
-    let scale = 2;
-    let new_value = money
-        .value
-        .round_dp_with_strategy(scale, rust_decimal::RoundingStrategy::MidpointNearestEven);
-    Money {
-        value: new_value,
-        code: money.code,
+impl Display for LoanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoanError::InvertedDates {
+                start_date,
+                end_date,
+            } => f.write_fmt(format_args!(
+                "Error end date {} is before start date {}",
+                end_date, start_date
+            )),
+            LoanError::NonPositivePrincipal(loan_amount) => f.write_fmt(format_args!(
+                "Error loan amount {} is not positive",
+                loan_amount
+            )),
+            LoanError::RateOutOfBounds { rate, limits } => f.write_fmt(format_args!(
+                "Error all-in rate {}% is outside {}% to {}%",
+                rate, limits.min, limits.max
+            )),
+            LoanError::UnsupportedCurrency(currency) => f.write_fmt(format_args!(
+                "Error currency {} has no minor units",
+                currency
+            )),
+            LoanError::MissingFixing(start_date) => f.write_fmt(format_args!(
+                "Error no base rate fixing on or before the start date {}",
+                start_date
+            )),
+            LoanError::Rfr(e) => e.fmt(f),
+            LoanError::RfrDayBasisMismatch {
+                day_basis,
+                day_count,
+            } => f.write_fmt(format_args!(
+                "Error RFR day basis of {} days contradicts the {} day count",
+                day_basis, day_count
+            )),
+            LoanError::FloorAboveCap { floor, cap } => {
+                f.write_fmt(format_args!("Error floor {}% is above cap {}%", floor, cap))
+            }
+            LoanError::BalloonResidualOutOfRange(residual_percentage) => f.write_fmt(format_args!(
+                "Error balloon residual {}% is not between 0 and 100",
+                residual_percentage
+            )),
+            LoanError::EventOutsideTerm(event) => f.write_fmt(format_args!(
+                "Error {} on {} is outside the loan term",
+                event, event.date
+            )),
+            LoanError::AboveFacilityLimit { date, drawn, limit } => f.write_fmt(format_args!(
+                "Error drawn balance {} on {} is above the limit {}",
+                drawn, date, limit
+            )),
+            LoanError::InvalidRollDay(roll_day) => f.write_fmt(format_args!(
+                "Error roll day {} is not a day of the month from 1 to 31",
+                roll_day
+            )),
+        }
     }
 }
 
-#[derive(Debug)]
-pub struct Entry {
-    pub daily_interest_without_margin: Money,
-    pub daily_interest_with_margin: Money,
-    pub accrual_date: NaiveDate,
-    pub days_elapsed: u64,
-}
+impl Error for LoanError {}
 
+/// Error reading a loan file, with the line it occurred on
+#[cfg(feature = "loan-file")]
 #[derive(Debug)]
-pub struct Schedule {
-    pub entries: std::vec::Vec<Entry>,
+pub struct LoanFileError {
+    line: usize,
+    message: String,
+    unknown_currency: Option<UnknownCurrencyError>,
 }
 
-impl Iterator for Schedule {
-    type Item = Entry;
+#[cfg(feature = "loan-file")]
+impl LoanFileError {
+    fn new(line: usize, message: String) -> Self {
+        Self {
+            line,
+            message,
+            unknown_currency: None,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.entries.pop()
+    /// Line of the file the error occurred on, 0 when it is not tied to a line
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The currency of the file when it is not in the ISO 4217 list, also
+    /// given as the source of the error
+    pub fn unknown_currency(&self) -> Option<&UnknownCurrencyError> {
+        self.unknown_currency.as_ref()
     }
 }
 
-impl From<Vec<Entry>> for Schedule {
-    fn from(entries: Vec<Entry>) -> Self {
-        Schedule { entries }
+#[cfg(feature = "loan-file")]
+impl Display for LoanFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error reading loan file on line {}: {}",
+            self.line, self.message
+        ))
     }
 }
 
-#[derive(Debug)]
-pub struct TotalInterest {
-    pub with_margin: Money,
-    pub without_margin: Money,
+#[cfg(feature = "loan-file")]
+impl Error for LoanFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.unknown_currency
+            .as_ref()
+            .map(|e| e as &(dyn Error + 'static))
+    }
+}
+
+// Currency of a loan file read on its own, as deserializers only pass on the
+// message of an unknown currency error
+#[cfg(feature = "loan-file")]
+#[derive(serde::Deserialize)]
+struct LoanFileCurrency {
+    currency: String,
+}
+
+#[cfg(feature = "loan-file")]
+#[derive(Clone, Copy)]
+enum LoanFileFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+#[cfg(feature = "loan-file")]
+impl LoanFileFormat {
+    fn parse<T: serde::de::DeserializeOwned>(self, contents: &str) -> Result<T, LoanFileError> {
+        match self {
+            LoanFileFormat::Toml => toml::from_str(contents).map_err(|e| {
+                // Spans are byte offsets into the file
+                let line = e
+                    .span()
+                    .map_or(0, |span| contents[..span.start].matches('\n').count() + 1);
+                LoanFileError::new(line, e.message().into())
+            }),
+            LoanFileFormat::Yaml => serde_yaml::from_str(contents).map_err(|e| {
+                let line = e.location().map_or(0, |location| location.line());
+                LoanFileError::new(line, e.to_string())
+            }),
+            LoanFileFormat::Json => serde_json::from_str(contents)
+                .map_err(|e| LoanFileError::new(e.line(), e.to_string())),
+        }
+    }
 }
 
+/// Terms of a loan, built with `Loan::new` and the `with_*` methods
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Loan {
+    /// First day interest accrues
     pub start_date: NaiveDate,
+    /// Last day interest accrues, when the loan is repaid
     pub end_date: NaiveDate,
+    /// Principal drawn at the start, or the limit drawn of a revolving facility
     pub loan_amount: Decimal,
-    pub base_rate: Decimal,
+    /// Base rate fixings, or overnight fixings for RFR loans, or a single rate
+    pub base_rate: RateSchedule,
+    /// Margin over the base rate, as a percentage
     pub margin: Decimal,
+    /// Currency of all amounts of the loan
     pub currency: CurrencyCode,
+    /// How days accrue as a fraction of a year
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub day_count: DayCountConvention,
+    /// How accrued interest is added to the balance
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub compounding: CompoundingMethod,
+    /// Compounds the base rate fixings in arrears as an overnight RFR
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rfr: Option<RfrTerms>,
+    /// Floor and cap on the base rate
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rate_bounds: RateBounds,
+    /// How principal is repaid
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub repayment: RepaymentProfile,
+    /// How often interest and principal are paid, loans without a frequency
+    /// pay everything on the end date
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub payment_frequency: Option<Frequency>,
+    /// Stub, roll day and end of month rules for the payment periods
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub period_rules: PeriodRules,
+    /// Prepayments and drawdowns, sorted by date
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "crate::event::deserialize_sorted")
+    )]
+    pub events: Vec<LoanEvent>,
+    /// Term loan or revolving credit facility
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub facility: FacilityType,
+    /// Holidays used to adjust period ends and payment dates
+    #[cfg_attr(feature = "serde", serde(default = "HolidayCalendar::weekends_only"))]
+    pub calendar: HolidayCalendar,
+    /// How period ends and payment dates falling on holidays move
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub business_day_convention: BusinessDayConvention,
+    /// How interest and fees are rounded
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rounding: RoundingPolicy,
 }
 
 impl Loan {
+    /// A bullet term loan accruing simple interest under ACT/ACT ISDA,
+    /// configured further with the `with_*` methods
     pub fn new(
         start_date: NaiveDate,
         end_date: NaiveDate,
         loan_amount: Decimal,
-        base_rate: Decimal,
+        base_rate: RateSchedule,
         margin: Decimal,
         currency: CurrencyCode,
     ) -> Self {
@@ -170,77 +499,1782 @@ impl Loan {
             base_rate,
             margin,
             currency,
+            day_count: DayCountConvention::default(),
+            compounding: CompoundingMethod::default(),
+            rfr: None,
+            rate_bounds: RateBounds::default(),
+            repayment: RepaymentProfile::default(),
+            payment_frequency: None,
+            period_rules: PeriodRules::default(),
+            events: Vec::new(),
+            facility: FacilityType::Term,
+            calendar: HolidayCalendar::weekends_only(),
+            business_day_convention: BusinessDayConvention::Unadjusted,
+            rounding: RoundingPolicy::default(),
+        }
+    }
+
+    /// Like [`Loan::new`] but rejects terms that cannot be accrued, checking
+    /// rates against the default [`RateLimits`]
+    pub fn try_new(
+        start_date: NaiveDate,
+        end_date: NaiveDate,
+        loan_amount: Decimal,
+        base_rate: RateSchedule,
+        margin: Decimal,
+        currency: CurrencyCode,
+    ) -> Result<Self, LoanError> {
+        let loan = Self::new(
+            start_date,
+            end_date,
+            loan_amount,
+            base_rate,
+            margin,
+            currency,
+        );
+        loan.validate(&RateLimits::default())?;
+        Ok(loan)
+    }
+
+    /// Checks the dates, principal and currency, that every base rate fixing
+    /// plus the margin is within `limits` and that the schedule can be
+    /// accrued: fixings from the start date, RFR fixings for every interest
+    /// period, rate bounds, balloon residual, events within the term and a
+    /// drawn balance within any facility limit
+    pub fn validate(&self, limits: &RateLimits) -> Result<(), LoanError> {
+        if self.end_date < self.start_date {
+            return Err(LoanError::InvertedDates {
+                start_date: self.start_date,
+                end_date: self.end_date,
+            });
+        }
+        if self.loan_amount <= Decimal::ZERO {
+            return Err(LoanError::NonPositivePrincipal(self.loan_amount));
+        }
+        if let Some(rate) = self
+            .base_rate
+            .fixings()
+            .iter()
+            .map(|fixing| fixing.rate + self.margin)
+            .find(|rate| *rate < limits.min || *rate > limits.max)
+        {
+            return Err(LoanError::RateOutOfBounds {
+                rate,
+                limits: *limits,
+            });
+        }
+        if self.currency.minor_units().is_none() {
+            return Err(LoanError::UnsupportedCurrency(self.currency));
+        }
+        self.check_accruable()?;
+        if let Some(rfr) = self.rfr {
+            let day_count_basis = match self.day_count {
+                DayCountConvention::Act365Fixed => Some(365),
+                DayCountConvention::Act360 => Some(360),
+                _ => None,
+            };
+            if day_count_basis.is_some_and(|day_count_basis| day_count_basis != rfr.day_basis) {
+                return Err(LoanError::RfrDayBasisMismatch {
+                    day_basis: rfr.day_basis,
+                    day_count: self.day_count,
+                });
+            }
+            for (start, end) in interest_periods(self.start_date, &self.payment_dates()) {
+                rfr.check(&self.base_rate, start, end)
+                    .map_err(LoanError::Rfr)?;
+            }
         }
+        if let (Some(floor), Some(cap)) = (self.rate_bounds.floor, self.rate_bounds.cap) {
+            // An all-in floor bounds the base rate at the floor less the margin
+            let base_floor = match self.rate_bounds.floor_basis {
+                FloorBasis::BaseRate => floor,
+                FloorBasis::AllIn => floor - self.margin,
+            };
+            if base_floor > cap {
+                return Err(LoanError::FloorAboveCap { floor, cap });
+            }
+        }
+        if let RepaymentProfile::Balloon {
+            residual_percentage,
+        } = self.repayment
+        {
+            if residual_percentage < Decimal::ZERO || residual_percentage > Decimal::from(100) {
+                return Err(LoanError::BalloonResidualOutOfRange(residual_percentage));
+            }
+        }
+        if let Some(event) = self
+            .events
+            .iter()
+            .find(|event| event.date < self.start_date || event.date > self.end_date)
+        {
+            return Err(LoanError::EventOutsideTerm(*event));
+        }
+        if let FacilityType::Revolving(facility) = &self.facility {
+            // Drawdowns and capitalised interest raise the balance after the
+            // start, so the days are accrued to find the first one above
+            let above_limit = |date: NaiveDate, drawn: Decimal| {
+                (drawn > facility.limit).then_some(LoanError::AboveFacilityLimit {
+                    date,
+                    drawn,
+                    limit: facility.limit,
+                })
+            };
+            if let Some(e) = above_limit(self.start_date, self.loan_amount) {
+                return Err(e);
+            }
+            if let Some(e) = Accruals::new(self)?
+                .find_map(|entry| above_limit(entry.accrual_date, entry.opening_balance.value))
+            {
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    // Terms the accruals cannot do without: a roll day the periods can end
+    // on and a fixing for the start date, later dates reuse the last one
+    fn check_accruable(&self) -> Result<(), LoanError> {
+        if let Some(roll_day) = self.period_rules.roll_day {
+            if !(1..=31).contains(&roll_day) {
+                return Err(LoanError::InvalidRollDay(roll_day));
+            }
+        }
+        if self.base_rate.rate_on(self.start_date).is_none() {
+            return Err(LoanError::MissingFixing(self.start_date));
+        }
+        Ok(())
+    }
+
+    /// Day count convention used to accrue interest
+    pub fn with_day_count(mut self, day_count: DayCountConvention) -> Self {
+        self.day_count = day_count;
+        self
+    }
+
+    /// Compounding method used to capitalise interest
+    pub fn with_compounding(mut self, compounding: CompoundingMethod) -> Self {
+        self.compounding = compounding;
+        self
+    }
+
+    /// Accrues a risk free rate compounded in arrears, the base rate fixings
+    /// being the overnight rates
+    pub fn with_rfr(mut self, rfr: RfrTerms) -> Self {
+        self.rfr = Some(rfr);
+        self
+    }
+
+    /// Floors and caps the base rate before the margin is added
+    pub fn with_rate_bounds(mut self, rate_bounds: RateBounds) -> Self {
+        self.rate_bounds = rate_bounds;
+        self
+    }
+
+    /// Repays principal by `repayment` on payments every `payment_frequency`,
+    /// or once at the end date when there is no frequency
+    pub fn with_repayment(
+        mut self,
+        repayment: RepaymentProfile,
+        payment_frequency: Option<Frequency>,
+    ) -> Self {
+        self.repayment = repayment;
+        self.payment_frequency = payment_frequency;
+        self
+    }
+
+    /// Prepayments, repayment and drawdowns, applied in date order
+    pub fn with_events(mut self, mut events: Vec<LoanEvent>) -> Self {
+        events.sort_by_key(|event| event.date);
+        self.events = events;
+        self
+    }
+
+    /// Stub, roll day and end of month rules for the interest periods
+    pub fn with_period_rules(mut self, period_rules: PeriodRules) -> Self {
+        self.period_rules = period_rules;
+        self
+    }
+
+    /// Makes the loan a revolving facility, drawn at `loan_amount` and
+    /// redrawn or repaid through events
+    pub fn with_facility(mut self, facility: FacilityType) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Business days used to adjust period ends and count payment delays
+    pub fn with_calendar(
+        mut self,
+        calendar: HolidayCalendar,
+        business_day_convention: BusinessDayConvention,
+    ) -> Self {
+        self.calendar = calendar;
+        self.business_day_convention = business_day_convention;
+        self
+    }
+
+    /// Rounding strategy, precision and stage for interest and fees
+    pub fn with_rounding(mut self, rounding: RoundingPolicy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    /// The loan with the terms given in `overrides` replacing its own. Fails
+    /// on RFR terms for a loan without RFR compounding, or on fees for a term
+    /// loan. The loan is not validated.
+    pub fn with_overrides(self, overrides: &LoanOverrides) -> Result<Self, OverrideError> {
+        overrides.apply(self)
+    }
+
+    /// Reads a loan from a TOML, YAML or JSON file, picked by its `.toml`,
+    /// `.yaml`, `.yml` or `.json` extension. The file holds the serialized
+    /// fields of the loan, terms left out take the same defaults as
+    /// `Loan::new`. The loan is not validated.
+    #[cfg(feature = "loan-file")]
+    pub fn from_file(path: &Path) -> Result<Self, LoanFileError> {
+        let contents =
+            fs::read_to_string(path).map_err(|e| LoanFileError::new(0, e.to_string()))?;
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        let format = match extension.as_deref() {
+            Some("toml") => LoanFileFormat::Toml,
+            Some("yaml" | "yml") => LoanFileFormat::Yaml,
+            Some("json") => LoanFileFormat::Json,
+            _ => {
+                return Err(LoanFileError::new(
+                    0,
+                    format!(
+                        "unknown format of {}, expected a .toml, .yaml, .yml or .json file",
+                        path.display()
+                    ),
+                ))
+            }
+        };
+        format.parse(&contents).map_err(|mut error| {
+            // Read the currency again on its own to tell whether it is unknown
+            if let Ok(LoanFileCurrency { currency }) = format.parse(&contents) {
+                error.unknown_currency = CurrencyCode::try_from(currency.as_str()).err();
+            }
+            error
+        })
+    }
+
+    /// Ends of the regular payment periods counted from the start date and
+    /// adjusted to business days, the last one always being the end date
+    pub fn payment_dates(&self) -> Vec<NaiveDate> {
+        let period_ends = match self.payment_frequency {
+            Some(frequency) => {
+                self.period_rules
+                    .period_ends(self.start_date, self.end_date, frequency)
+            }
+            None => vec![self.end_date],
+        };
+        let mut dates: Vec<NaiveDate> = Vec::new();
+        for unadjusted in &period_ends[..period_ends.len() - 1] {
+            let date = self
+                .calendar
+                .adjust(*unadjusted, self.business_day_convention);
+            if date > self.start_date
+                && date < self.end_date
+                && dates.last().map_or(true, |last| *last < date)
+            {
+                dates.push(date);
+            }
+        }
+        dates.push(self.end_date);
+        dates
+    }
+
+    /// Date the amounts due at the end of a period are paid, the period end
+    /// adjusted to a business day and moved on by any RFR payment delay
+    pub fn payment_date(&self, period_end: NaiveDate) -> NaiveDate {
+        let date = self
+            .calendar
+            .adjust(period_end, self.business_day_convention);
+        match self.rfr {
+            Some(rfr) => self
+                .calendar
+                .add_business_days(date, rfr.payment_delay_days),
+            None => date,
+        }
+    }
+
+    /// Interest and fees over the whole loan, the same totals as
+    /// `Schedule::new(self).calculate_interest()` without building the
+    /// schedule. Runs of days sharing a balance, rate and year fraction are
+    /// accrued together and still match the schedule to the last digit
+    /// whatever the rounding stage. `None` when the loan has no days to
+    /// accrue. Fails as `Accruals::new` does.
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use oneiro::{CurrencyCode, Loan, RateSchedule, Schedule};
+    /// use rust_decimal::Decimal;
+    ///
+    /// let loan = Loan::new(
+    ///     NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+    ///     NaiveDate::from_ymd_opt(2053, 12, 31).unwrap(),
+    ///     Decimal::from(250_000),
+    ///     RateSchedule::flat(Decimal::new(425, 2)),
+    ///     Decimal::new(175, 2),
+    ///     CurrencyCode::GBP,
+    /// );
+    /// let total_interest = loan.total_interest().unwrap().unwrap();
+    /// let schedule = Schedule::new(&loan).unwrap().calculate_interest().unwrap().unwrap();
+    /// assert_eq!(total_interest.with_margin, schedule.with_margin);
+    /// assert_eq!(total_interest.without_margin, schedule.without_margin);
+    /// ```
+    pub fn total_interest(&self) -> Result<Option<TotalInterest>, LoanError> {
+        Ok(Accruals::new(self)?.total_interest())
     }
 }
 
-// Calculates daily interest without margin
-fn daily_interest_without_margin(loan: &Loan) -> Money {
-    let daily_rate = loan.base_rate / Decimal::from(DAYS_IN_YEAR) / Decimal::from(100);
-    Money {
-        value: (loan.loan_amount * daily_rate).into(),
-        code: loan.currency,
+// Regular payment period around the accrual date, stubs are measured against
+// the notional regular period they fall in. Loans without a payment frequency
+// use annual periods.
+fn reference_period(loan: &Loan, accrual_date: NaiveDate) -> ReferencePeriod {
+    let frequency = loan.payment_frequency.unwrap_or(Frequency::Annual);
+    let (start, end) =
+        loan.period_rules
+            .reference_period(loan.start_date, loan.end_date, frequency, accrual_date);
+    ReferencePeriod {
+        start,
+        end,
+        frequency: frequency.periods_per_year(),
+        maturity: loan.end_date,
     }
 }
 
-// calculates the daily interest with margin
-fn daily_interest_with_margin(loan: &Loan) -> Money {
-    let daily_rate =
-        (loan.base_rate + loan.margin) / Decimal::from(DAYS_IN_YEAR) / Decimal::from(100);
-    Money {
-        value: (loan.loan_amount * daily_rate).into(),
-        code: loan.currency,
+// Interest periods `[start, end)` running from the start date or the day
+// after a payment date to the day after the next payment date
+fn interest_periods(
+    start_date: NaiveDate,
+    payment_dates: &[NaiveDate],
+) -> impl Iterator<Item = (NaiveDate, NaiveDate)> + '_ {
+    let ends = payment_dates.iter().map(|date| *date + Duration::days(1));
+    std::iter::once(start_date).chain(ends.clone()).zip(ends)
+}
+
+fn days_in_year(loan: &Loan, accrual_date: NaiveDate) -> u32 {
+    loan.day_count
+        .days_in_year(accrual_date, &reference_period(loan, accrual_date))
+}
+
+// Fraction of a year accrued on the accrual date under the loan's day count
+fn daily_year_fraction(loan: &Loan, accrual_date: NaiveDate) -> Decimal {
+    loan.day_count
+        .daily_year_fraction(accrual_date, &reference_period(loan, accrual_date))
+}
+
+// Calculates daily interest without margin on the interest-bearing balance
+fn daily_interest_without_margin(
+    loan: &Loan,
+    balance: Decimal,
+    base_rate: Decimal,
+    year_fraction: Decimal,
+) -> Money {
+    let growth = loan.compounding.growth(base_rate, year_fraction);
+    Money::new(balance * growth, loan.currency)
+}
+
+// calculates the daily interest with margin on the interest-bearing balance
+fn daily_interest_with_margin(
+    loan: &Loan,
+    balance: Decimal,
+    base_rate: Decimal,
+    year_fraction: Decimal,
+) -> Money {
+    let growth = loan
+        .compounding
+        .growth(base_rate + loan.margin, year_fraction);
+    Money::new(balance * growth, loan.currency)
+}
+
+// Rounds the amount accrued for a payment, leaving in `accrued` the rounding
+// difference carried into the next period
+fn settle(loan: &Loan, accrued: &mut Decimal) -> Money {
+    let paid = loan.rounding.round(Money::new(*accrued, loan.currency));
+    *accrued = match loan.rounding.stage {
+        RoundingStage::Period => Decimal::ZERO,
+        RoundingStage::Daily | RoundingStage::Total => *accrued - paid.value,
+    };
+    paid
+}
+
+// Undrawn balance, commitment fee and utilisation fee for the day, all zero
+// for term loans
+fn daily_fees(loan: &Loan, drawn: Decimal, year_fraction: Decimal) -> (Money, Money, Money) {
+    let money = |value| Money::new(value, loan.currency);
+    match &loan.facility {
+        FacilityType::Term => (
+            money(Decimal::ZERO),
+            money(Decimal::ZERO),
+            money(Decimal::ZERO),
+        ),
+        FacilityType::Revolving(facility) => {
+            let year_fraction = year_fraction / Decimal::from(100);
+            let undrawn = facility.undrawn(drawn);
+            (
+                money(undrawn),
+                money(undrawn * facility.commitment_fee_rate(loan.margin) * year_fraction),
+                money(drawn * facility.utilisation_fee_rate(drawn) * year_fraction),
+            )
+        }
     }
 }
 
-impl Schedule {
-    pub fn new(loan: &Loan) -> Self {
-        let duration = loan.end_date.signed_duration_since(loan.start_date);
-        println!("{:?}", loan);
-        println!("duration = {}", duration.num_days());
-        (0..=duration.num_days() as u64)
-            .map(|days_elapsed| {
-                let accrual_date = loan.start_date + Duration::days(days_elapsed as i64);
-                let daily_interest_without_margin = daily_interest_without_margin(loan);
-                let daily_interest_with_margin = daily_interest_with_margin(loan);
-                Entry {
-                    daily_interest_without_margin,
-                    daily_interest_with_margin,
+// Adds `amount` to `sum` once for each of `days`. Multiplied out when the sum
+// keeps every digit, otherwise added a day at a time so it loses precision
+// where adding up the daily entries does.
+fn accrue_days(sum: &mut Decimal, amount: Decimal, days: u64) {
+    let scale = sum.scale().max(amount.scale());
+    let mut rescaled = *sum;
+    rescaled.rescale(scale);
+    let total = amount
+        .checked_mul(Decimal::from(days))
+        .filter(|product| product.scale() == amount.scale())
+        .and_then(|product| sum.checked_add(product));
+    match total {
+        Some(total) if total.scale() == scale && rescaled.scale() == scale => *sum = total,
+        _ => {
+            for _ in 0..days {
+                *sum += amount;
+            }
+        }
+    }
+}
+
+/// Daily entries of a loan, accrued one day at a time as they are iterated
+/// so a schedule can be read without holding every day
+#[derive(Debug)]
+pub struct Accruals<'a> {
+    loan: &'a Loan,
+    payment_dates: Vec<NaiveDate>,
+    compounded_rates: Option<Vec<CompoundedRate>>,
+    // Days from the start date to the end date and the next day to accrue
+    days: u64,
+    days_elapsed: u64,
+    // The balance compounds on the all-in interest, the interest without
+    // margin is shown against the same balance
+    balance: Decimal,
+    accrued: Decimal,
+    accrued_fees: Decimal,
+    payments_made: usize,
+    events_applied: usize,
+}
+
+impl<'a> Accruals<'a> {
+    /// Accrues `loan` from its start date. Fails on a roll day outside 1 to
+    /// 31, without a base rate fixing on or before the start date or when the
+    /// RFR fixings cannot be compounded, the other checks of
+    /// [`Loan::validate`] are left to the caller.
+    pub fn new(loan: &'a Loan) -> Result<Self, LoanError> {
+        loan.check_accruable()?;
+        let days = (loan
+            .end_date
+            .signed_duration_since(loan.start_date)
+            .num_days()
+            + 1)
+        .max(0);
+        debug!("calculating schedule for {:?}", loan);
+        debug!(
+            "accruing {} days from {} to {}",
+            days, loan.start_date, loan.end_date
+        );
+
+        let payment_dates = loan.payment_dates();
+        debug!("payment dates {:?}", payment_dates);
+        let compounded_rates = loan
+            .rfr
+            .map(|rfr| {
+                interest_periods(loan.start_date, &payment_dates).try_fold(
+                    Vec::new(),
+                    |mut rates, (start, end)| {
+                        rates.extend(rfr.compound(&loan.base_rate, start, end)?);
+                        Ok(rates)
+                    },
+                )
+            })
+            .transpose()
+            .map_err(LoanError::Rfr)?;
+        Ok(Self {
+            loan,
+            payment_dates,
+            compounded_rates,
+            days: days as u64,
+            days_elapsed: 0,
+            balance: loan.loan_amount,
+            accrued: Decimal::zero(),
+            accrued_fees: Decimal::zero(),
+            payments_made: 0,
+            events_applied: 0,
+        })
+    }
+
+    // Applies the events taking effect by the start of the accrual date
+    fn apply_events(&mut self, accrual_date: NaiveDate) -> &'a [LoanEvent] {
+        let loan = self.loan;
+        let events_start = self.events_applied;
+        while loan
+            .events
+            .get(self.events_applied)
+            .is_some_and(|event| event.date <= accrual_date)
+        {
+            self.balance = loan.events[self.events_applied].apply(self.balance);
+            debug!(
+                "{}: {} applied, balance {}",
+                accrual_date, loan.events[self.events_applied], self.balance
+            );
+            self.events_applied += 1;
+        }
+        &loan.events[events_start..self.events_applied]
+    }
+
+    // Base rate observed for the next day to accrue, RFR loans accrue the
+    // daily non-cumulative compounded rate
+    fn raw_base_rate(&self, accrual_date: NaiveDate) -> (Decimal, Option<CompoundedRate>) {
+        let compounded_rate = self
+            .compounded_rates
+            .as_ref()
+            .map(|rates| rates[self.days_elapsed as usize]);
+        let raw_base_rate = match compounded_rate {
+            Some(compounded_rate) => compounded_rate.daily_rate,
+            None => self
+                .loan
+                .base_rate
+                .rate_on(accrual_date)
+                .expect("Accruals::new checks there is a fixing on the start date"),
+        };
+        (raw_base_rate, compounded_rate)
+    }
+
+    // Capitalises the interest and makes the payment due at the end of the
+    // accrual date, returning the interest capitalised
+    fn end_day(
+        &mut self,
+        accrual_date: NaiveDate,
+        base_rate: Decimal,
+    ) -> (Decimal, Option<Payment>) {
+        let loan = self.loan;
+        let capitalised_interest = if loan.compounding.capitalises(loan.start_date, accrual_date) {
+            std::mem::take(&mut self.accrued)
+        } else {
+            Decimal::zero()
+        };
+        if !capitalised_interest.is_zero() {
+            debug!(
+                "{}: capitalised {}, balance {}",
+                accrual_date,
+                capitalised_interest,
+                self.balance + capitalised_interest
+            );
+        }
+        self.balance += capitalised_interest;
+
+        let payment =
+            (self.payment_dates.get(self.payments_made) == Some(&accrual_date)).then(|| {
+                let remaining_payments = (self.payment_dates.len() - self.payments_made) as u32;
+                self.payments_made += 1;
+
+                let interest = settle(loan, &mut self.accrued);
+                let fees = settle(loan, &mut self.accrued_fees);
+
+                let periods_per_year = loan
+                    .payment_frequency
+                    .map_or(1, |frequency| frequency.periods_per_year());
+                let periodic_rate = (base_rate + loan.margin)
+                    / Decimal::from(100)
+                    / Decimal::from(periods_per_year);
+                let principal_due = loan.repayment.principal_due(
+                    self.balance,
+                    loan.loan_amount,
+                    interest.value,
+                    periodic_rate,
+                    remaining_payments,
+                );
+                let principal = if remaining_payments == 1 {
+                    principal_due
+                } else {
+                    loan.rounding
+                        .round(Money::new(principal_due, loan.currency))
+                        .value
+                };
+                self.balance -= principal;
+                debug!(
+                    "{}: payment {} of {}, principal {}, interest {}, fees {}, outstanding {}",
                     accrual_date,
-                    days_elapsed,
+                    self.payments_made,
+                    self.payment_dates.len(),
+                    principal,
+                    interest.value,
+                    fees.value,
+                    self.balance
+                );
+
+                Payment {
+                    period_end: accrual_date,
+                    payment_date: loan.payment_date(accrual_date),
+                    principal: Money::new(principal, loan.currency),
+                    interest,
+                    fees,
+                    outstanding_principal: Money::new(self.balance, loan.currency),
                 }
-            })
-            .collect::<Vec<_>>()
-            .into()
+            });
+        (capitalised_interest, payment)
+    }
+
+    // Last day from the accrual date that accrues the same amounts: before
+    // the next event or fixing, on the next capitalisation or payment at the
+    // latest and with the same year fraction. RFR rates change every day.
+    fn run_end(&self, accrual_date: NaiveDate) -> NaiveDate {
+        let loan = self.loan;
+        if self.compounded_rates.is_some() {
+            return accrual_date;
+        }
+        let day_before = |date: NaiveDate| date - Duration::days(1);
+        let period = reference_period(loan, accrual_date);
+        [
+            self.payment_dates.get(self.payments_made).copied(),
+            loan.compounding
+                .next_capitalisation(loan.start_date, accrual_date),
+            loan.events
+                .get(self.events_applied)
+                .map(|event| day_before(event.date)),
+            loan.base_rate.next_fixing(accrual_date).map(day_before),
+            Some(
+                loan.day_count
+                    .same_daily_year_fraction_until(accrual_date, &period),
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(loan.end_date, NaiveDate::min)
     }
 
-    pub fn calculate_interest(&self) -> Option<TotalInterest> {
-        if self.entries.is_empty() {
+    // Interest and fees of the loan as `Schedule::calculate_interest` adds
+    // them up, accruing each run of days with the same amounts at once
+    fn total_interest(mut self) -> Option<TotalInterest> {
+        let loan = self.loan;
+        let money = |value| Money::new(value, loan.currency);
+        if self.days == 0 {
             return None;
         }
 
-        let (interest_with_margin, interest_without_margin) = self.entries.iter().fold(
-            (Decimal::zero(), Decimal::zero()),
-            |(interest_with_margin, interest_without_margin), entry| {
-                (
-                    (interest_with_margin + bankers_round(entry.daily_interest_with_margin)).value,
-                    (interest_without_margin + bankers_round(entry.daily_interest_without_margin))
-                        .value,
-                )
-            },
-        );
+        // Interest with and without margin, commitment and utilisation fees
+        // of the current period and of the periods paid so far
+        let mut period = [Decimal::ZERO; 4];
+        let mut total = [Decimal::ZERO; 4];
+        while self.days_elapsed < self.days {
+            let accrual_date = loan.start_date + Duration::days(self.days_elapsed as i64);
+            self.apply_events(accrual_date);
+            let (raw_base_rate, _) = self.raw_base_rate(accrual_date);
+            let base_rate = loan.rate_bounds.apply(raw_base_rate, loan.margin);
+            let year_fraction = daily_year_fraction(loan, accrual_date);
+            let (_, commitment_fee, utilisation_fee) =
+                daily_fees(loan, self.balance, year_fraction);
+            let daily = [
+                daily_interest_with_margin(loan, self.balance, base_rate, year_fraction),
+                daily_interest_without_margin(loan, self.balance, base_rate, year_fraction),
+                commitment_fee,
+                utilisation_fee,
+            ]
+            .map(|amount| loan.rounding.round_daily(amount).value);
+
+            let last_day = self.run_end(accrual_date);
+            let days = last_day.signed_duration_since(accrual_date).num_days() as u64 + 1;
+            trace!(
+                "{} to {}: balance {}, base rate {}, year fraction {}, interest {} a day",
+                accrual_date,
+                last_day,
+                self.balance,
+                base_rate,
+                year_fraction,
+                daily[0]
+            );
+            for (sum, amount) in period.iter_mut().zip(daily) {
+                accrue_days(sum, amount, days);
+            }
+            accrue_days(&mut self.accrued, daily[0], days);
+            accrue_days(&mut self.accrued_fees, daily[2] + daily[3], days);
+            self.days_elapsed += days;
 
-        let currency_code = self.entries[0].daily_interest_with_margin.code;
+            if self.end_day(last_day, base_rate).1.is_some() {
+                for (total, period) in total.iter_mut().zip(&mut period) {
+                    // The days are already rounded, rounding them again
+                    // changes nothing
+                    *total += loan
+                        .rounding
+                        .round_period(loan.currency, [money(std::mem::take(period))])
+                        .expect("amounts of one loan share its currency")
+                        .value;
+                }
+            }
+        }
 
+        let [with_margin, without_margin, commitment_fee, utilisation_fee] = total.map(|total| {
+            loan.rounding
+                .round_total(loan.currency, [money(total)])
+                .expect("amounts of one loan share its currency")
+        });
         Some(TotalInterest {
-            with_margin: Money {
-                value: interest_with_margin,
-                code: currency_code,
+            with_margin,
+            without_margin,
+            commitment_fee,
+            utilisation_fee,
+        })
+    }
+}
+
+impl Iterator for Accruals<'_> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.days_elapsed >= self.days {
+            return None;
+        }
+        let loan = self.loan;
+        let days_elapsed = self.days_elapsed;
+        let accrual_date = loan.start_date + Duration::days(days_elapsed as i64);
+        let events = self.apply_events(accrual_date).to_vec();
+
+        let opening_balance = self.balance;
+        let (raw_base_rate, compounded_rate) = self.raw_base_rate(accrual_date);
+        let base_rate = loan.rate_bounds.apply(raw_base_rate, loan.margin);
+        let year_fraction = daily_year_fraction(loan, accrual_date);
+        let daily_interest_without_margin =
+            daily_interest_without_margin(loan, opening_balance, base_rate, year_fraction);
+        let daily_interest_with_margin =
+            daily_interest_with_margin(loan, opening_balance, base_rate, year_fraction);
+
+        let (undrawn_balance, commitment_fee, utilisation_fee) =
+            daily_fees(loan, opening_balance, year_fraction);
+        trace!(
+            "{}: balance {}, base rate {} (raw {}), year fraction {}, \
+             interest {} without margin {}, commitment fee {}, utilisation fee {}",
+            accrual_date,
+            opening_balance,
+            base_rate,
+            raw_base_rate,
+            year_fraction,
+            daily_interest_with_margin.value,
+            daily_interest_without_margin.value,
+            commitment_fee.value,
+            utilisation_fee.value
+        );
+
+        self.accrued += loan.rounding.round_daily(daily_interest_with_margin).value;
+        self.accrued_fees += loan.rounding.round_daily(commitment_fee).value
+            + loan.rounding.round_daily(utilisation_fee).value;
+        self.days_elapsed += 1;
+        let (capitalised_interest, payment) = self.end_day(accrual_date, base_rate);
+
+        Some(Entry {
+            daily_interest_without_margin,
+            daily_interest_with_margin,
+            accrual_date,
+            days_elapsed,
+            days_in_year: days_in_year(loan, accrual_date),
+            raw_base_rate,
+            base_rate,
+            events,
+            opening_balance: Money::new(opening_balance, loan.currency),
+            undrawn_balance,
+            commitment_fee,
+            utilisation_fee,
+            accrued_interest: Money::new(self.accrued, loan.currency),
+            capitalised_interest: Money::new(capitalised_interest, loan.currency),
+            compounded_rate,
+            payment,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.days - self.days_elapsed) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Accruals<'_> {}
+
+impl Schedule {
+    /// Accrues `loan` day by day, making payments at the end of each period.
+    /// `Accruals::new` gives the same entries one at a time and fails on the
+    /// same loans.
+    pub fn new(loan: &Loan) -> Result<Self, LoanError> {
+        Ok(Schedule {
+            entries: Accruals::new(loan)?.collect(),
+            rounding: loan.rounding,
+        })
+    }
+
+    /// Entries in date order, from either end
+    pub fn iter(&self) -> std::slice::Iter<'_, Entry> {
+        self.entries.iter()
+    }
+
+    /// Entries accruing from `from` to `to`, both included. Empty when no day
+    /// of the schedule falls between them.
+    ///
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use oneiro::{CurrencyCode, Loan, RateSchedule, Schedule};
+    /// use rust_decimal::Decimal;
+    ///
+    /// let date = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    /// let loan = Loan::new(
+    ///     date(1, 1),
+    ///     date(12, 31),
+    ///     Decimal::from(10_000),
+    ///     RateSchedule::flat(Decimal::from(5)),
+    ///     Decimal::from(2),
+    ///     CurrencyCode::EUR,
+    /// );
+    /// let schedule = Schedule::new(&loan).unwrap();
+    /// assert_eq!(schedule.iter().len(), 366);
+    /// assert_eq!(schedule.iter().next().unwrap().accrual_date, date(1, 1));
+    /// assert_eq!(schedule.iter().next_back().unwrap().accrual_date, date(12, 31));
+    ///
+    /// let march = schedule.entries_between(date(3, 1), date(3, 31));
+    /// assert_eq!(march.len(), 31);
+    /// assert_eq!(march[0].accrual_date, date(3, 1));
+    /// assert_eq!(schedule.entry_on(date(2, 29)).unwrap().days_elapsed, 59);
+    /// assert!(schedule.entries_between(date(4, 1), date(3, 1)).is_empty());
+    /// ```
+    pub fn entries_between(&self, from: NaiveDate, to: NaiveDate) -> &[Entry] {
+        let start = self
+            .entries
+            .partition_point(|entry| entry.accrual_date < from);
+        let end = self
+            .entries
+            .partition_point(|entry| entry.accrual_date <= to);
+        &self.entries[start..end.max(start)]
+    }
+
+    /// The entry accruing on `date`, `None` outside the schedule
+    pub fn entry_on(&self, date: NaiveDate) -> Option<&Entry> {
+        self.entries
+            .binary_search_by_key(&date, |entry| entry.accrual_date)
+            .ok()
+            .map(|index| &self.entries[index])
+    }
+
+    /// The schedule in a reporting `currency`, each entry converted at the
+    /// rate on its accrual date
+    pub fn convert(
+        &self,
+        fx_rates: &FxRates,
+        currency: CurrencyCode,
+    ) -> Result<Schedule, MissingFxRateError> {
+        let entries = self
+            .iter()
+            .map(|entry| entry.convert(fx_rates, currency))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Schedule {
+            entries,
+            rounding: self.rounding,
+        })
+    }
+
+    /// Interest periods, each ending on the day of its payment
+    pub fn periods(&self) -> Vec<Period<'_>> {
+        let mut periods = Vec::new();
+        let mut period_start = 0;
+        for (index, entry) in self.entries.iter().enumerate() {
+            if let Some(payment) = &entry.payment {
+                periods.push(Period {
+                    entries: &self.entries[period_start..=index],
+                    payment,
+                });
+                period_start = index + 1;
+            }
+        }
+        periods
+    }
+
+    /// Interest and fees of the whole schedule, rounded as the rounding policy
+    /// requires. `None` for an empty schedule.
+    pub fn calculate_interest(&self) -> Result<Option<TotalInterest>, CurrencyMismatchError> {
+        let Some(first) = self.entries.first() else {
+            return Ok(None);
+        };
+        let currency_code = first.daily_interest_with_margin.code;
+
+        // Days are rounded into periods and periods into the total as the
+        // rounding policy requires
+        let periods = self.periods();
+        let total = |amount: fn(&Entry) -> Money| {
+            let period_totals = periods
+                .iter()
+                .map(|period| {
+                    self.rounding
+                        .round_period(currency_code, period.entries.iter().map(amount))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            self.rounding.round_total(currency_code, period_totals)
+        };
+
+        Ok(Some(TotalInterest {
+            with_margin: total(|entry| entry.daily_interest_with_margin)?,
+            without_margin: total(|entry| entry.daily_interest_without_margin)?,
+            commitment_fee: total(|entry| entry.commitment_fee)?,
+            utilisation_fee: total(|entry| entry.utilisation_fee)?,
+        }))
+    }
+
+    /// Interest with margin of each period and of the whole loan before and
+    /// after rounding, the total comes last
+    pub fn rounding_differences(&self) -> Result<Vec<RoundingDifference>, CurrencyMismatchError> {
+        let Some(first) = self.entries.first() else {
+            return Ok(Vec::new());
+        };
+        let currency_code = first.daily_interest_with_margin.code;
+        let daily = |period: &Period| {
+            period
+                .entries
+                .iter()
+                .map(|entry| entry.daily_interest_with_margin)
+                .collect::<Vec<_>>()
+        };
+
+        let mut differences = self
+            .periods()
+            .iter()
+            .map(|period| {
+                Ok(RoundingDifference {
+                    period_end: Some(period.end_date()),
+                    unrounded: Money::sum(currency_code, daily(period))?,
+                    rounded: self.rounding.round_period(currency_code, daily(period))?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let total = RoundingDifference {
+            period_end: None,
+            unrounded: Money::sum(
+                currency_code,
+                differences.iter().map(|difference| difference.unrounded),
+            )?,
+            rounded: self.rounding.round_total(
+                currency_code,
+                differences.iter().map(|difference| difference.rounded),
+            )?,
+        };
+        differences.push(total);
+        Ok(differences)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::LoanEventKind;
+    use crate::facility::{CommitmentFee, RevolvingFacility, UtilisationTier};
+    use crate::rate_schedule::Fixing;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Records log messages of the threads that ask for them, so tests
+    // running alongside do not log into each other's records
+    struct CapturingLogger;
+
+    thread_local! {
+        static CAPTURED: std::cell::RefCell<Option<Vec<(log::Level, String)>>> =
+            const { std::cell::RefCell::new(None) };
+    }
+
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            CAPTURED.with(|captured| captured.borrow().is_some())
+        }
+
+        fn log(&self, record: &log::Record) {
+            CAPTURED.with(|captured| {
+                if let Some(records) = captured.borrow_mut().as_mut() {
+                    records.push((record.level(), record.args().to_string()));
+                }
+            });
+        }
+
+        fn flush(&self) {}
+    }
+
+    // Messages logged on this thread while `f` runs
+    fn captured_logs(f: impl FnOnce()) -> Vec<(log::Level, String)> {
+        static LOGGER: CapturingLogger = CapturingLogger;
+        static INIT: std::sync::Once = std::sync::Once::new();
+        INIT.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Trace);
+        });
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+        f();
+        CAPTURED.with(|captured| captured.borrow_mut().take().unwrap())
+    }
+
+    #[test]
+    fn schedules_log_each_step_and_every_day() {
+        let loan = Loan::new(
+            date(2024, 1, 1),
+            date(2024, 3, 31),
+            Decimal::from(1_000),
+            RateSchedule::flat(Decimal::from(5)),
+            Decimal::ZERO,
+            CurrencyCode::GBP,
+        )
+        .with_compounding(CompoundingMethod::Monthly)
+        .with_repayment(RepaymentProfile::Linear, Some(Frequency::Monthly))
+        .with_events(vec![LoanEvent {
+            date: date(2024, 2, 15),
+            kind: LoanEventKind::Prepayment(Decimal::from(100)),
+        }]);
+        let logs = captured_logs(|| {
+            Schedule::new(&loan).unwrap();
+        });
+        let debug = logs
+            .iter()
+            .filter(|(level, _)| *level == log::Level::Debug)
+            .map(|(_, message)| message.as_str())
+            .collect::<Vec<_>>();
+        assert!(debug.contains(&"accruing 91 days from 2024-01-01 to 2024-03-31"));
+        assert!(debug
+            .iter()
+            .any(|message| message.starts_with("2024-02-15: Prepayment 100.00 applied")));
+        assert!(debug
+            .iter()
+            .any(|message| message.starts_with("2024-01-31: capitalised ")));
+        assert_eq!(
+            debug
+                .iter()
+                .filter(|message| message.contains(": payment "))
+                .count(),
+            3
+        );
+        let days = logs
+            .iter()
+            .filter(|(level, _)| *level == log::Level::Trace)
+            .count();
+        assert_eq!(days, 91);
+    }
+
+    #[test]
+    fn totals_log_each_run_of_days_rather_than_every_day() {
+        let loan = Loan::new(
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            Decimal::from(1_000),
+            RateSchedule::flat(Decimal::from(5)),
+            Decimal::ZERO,
+            CurrencyCode::GBP,
+        );
+        let logs = captured_logs(|| {
+            loan.total_interest().unwrap();
+        });
+        let runs = logs
+            .iter()
+            .filter(|(level, _)| *level == log::Level::Trace)
+            .map(|(_, message)| message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(runs.len(), 1);
+        assert!(runs[0].starts_with("2024-01-01 to 2024-12-31: balance 1000"));
+    }
+
+    #[test]
+    fn accruals_across_a_leap_year_are_split_by_calendar_year() {
+        let loan = Loan::new(
+            date(2023, 7, 1),
+            date(2024, 6, 30),
+            Decimal::from(1_000_000),
+            RateSchedule::flat(Decimal::from(4)),
+            Decimal::ONE,
+            CurrencyCode::GBP,
+        )
+        .with_rounding(RoundingPolicy {
+            stage: RoundingStage::Total,
+            ..RoundingPolicy::default()
+        });
+        // 184 days of 2023 over 365 and 182 days of 2024 over 366
+        let years =
+            Decimal::from(184) / Decimal::from(365) + Decimal::from(182) / Decimal::from(366);
+        let interest = |rate: i64| {
+            Money::new(
+                (Decimal::from(1_000_000) * Decimal::new(rate, 2) * years).round_dp(2),
+                CurrencyCode::GBP,
+            )
+        };
+
+        let schedule = Schedule::new(&loan).unwrap();
+        let total_interest = schedule.calculate_interest().unwrap().unwrap();
+        assert_eq!(total_interest.with_margin, interest(5));
+        assert_eq!(total_interest.without_margin, interest(4));
+        assert_eq!(total_interest.with_margin.value, Decimal::new(5_006_887, 2));
+
+        let days_in_year = |day| schedule.entry_on(day).unwrap().days_in_year;
+        assert_eq!(days_in_year(date(2023, 12, 31)), 365);
+        assert_eq!(days_in_year(date(2024, 1, 1)), 366);
+        let closed_form = loan.total_interest().unwrap().unwrap();
+        assert_eq!(closed_form.with_margin, total_interest.with_margin);
+        assert_eq!(closed_form.without_margin, total_interest.without_margin);
+    }
+
+    fn one_year_loan() -> Loan {
+        Loan::new(
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            Decimal::from(1_000_000),
+            RateSchedule::flat(Decimal::new(35, 1)),
+            Decimal::from(2),
+            CurrencyCode::GBP,
+        )
+    }
+
+    #[test]
+    fn all_in_floors_are_compared_with_the_cap_less_the_margin() {
+        let bounds = |floor, floor_basis| RateBounds {
+            floor: Some(Decimal::from(floor)),
+            cap: Some(Decimal::from(4)),
+            floor_basis,
+        };
+        let limits = RateLimits::default();
+
+        let loan = one_year_loan().with_rate_bounds(bounds(5, FloorBasis::AllIn));
+        assert_eq!(loan.validate(&limits), Ok(()));
+
+        let loan = one_year_loan().with_rate_bounds(bounds(7, FloorBasis::AllIn));
+        assert_eq!(
+            loan.validate(&limits),
+            Err(LoanError::FloorAboveCap {
+                floor: Decimal::from(7),
+                cap: Decimal::from(4),
+            })
+        );
+
+        let loan = one_year_loan().with_rate_bounds(bounds(5, FloorBasis::BaseRate));
+        assert_eq!(
+            loan.validate(&limits),
+            Err(LoanError::FloorAboveCap {
+                floor: Decimal::from(5),
+                cap: Decimal::from(4),
+            })
+        );
+    }
+
+    #[test]
+    fn roll_days_must_be_days_of_the_month() {
+        let limits = RateLimits::default();
+        for roll_day in [0, 32] {
+            let loan = one_year_loan().with_period_rules(PeriodRules {
+                roll_day: Some(roll_day),
+                ..PeriodRules::default()
+            });
+            assert_eq!(
+                loan.validate(&limits),
+                Err(LoanError::InvalidRollDay(roll_day))
+            );
+        }
+        for roll_day in [1, 31] {
+            let loan = one_year_loan().with_period_rules(PeriodRules {
+                roll_day: Some(roll_day),
+                ..PeriodRules::default()
+            });
+            assert_eq!(loan.validate(&limits), Ok(()));
+        }
+    }
+
+    #[test]
+    fn rfr_day_bases_must_agree_with_the_day_count() {
+        let limits = RateLimits::default();
+        let rfr = |day_basis| RfrTerms {
+            day_basis,
+            ..RfrTerms::default()
+        };
+        for (day_count, day_basis) in [
+            (DayCountConvention::Act365Fixed, 365),
+            (DayCountConvention::Act360, 360),
+            (DayCountConvention::ActActIsda, 360),
+        ] {
+            let loan = one_year_loan()
+                .with_day_count(day_count)
+                .with_rfr(rfr(day_basis));
+            assert_eq!(loan.validate(&limits), Ok(()), "{}", day_count);
+        }
+        for (day_count, day_basis) in [
+            (DayCountConvention::Act365Fixed, 360),
+            (DayCountConvention::Act360, 365),
+        ] {
+            let loan = one_year_loan()
+                .with_day_count(day_count)
+                .with_rfr(rfr(day_basis));
+            assert_eq!(
+                loan.validate(&limits),
+                Err(LoanError::RfrDayBasisMismatch {
+                    day_basis,
+                    day_count
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn unvalidated_loans_that_cannot_be_accrued_are_errors() {
+        let late_fixings = Loan {
+            base_rate: RateSchedule::new(vec![Fixing {
+                effective_date: date(2024, 2, 1),
+                rate: Decimal::from(5),
+            }]),
+            ..one_year_loan()
+        };
+        assert_eq!(
+            Schedule::new(&late_fixings).unwrap_err(),
+            LoanError::MissingFixing(date(2024, 1, 1))
+        );
+        assert_eq!(
+            late_fixings.total_interest().unwrap_err(),
+            LoanError::MissingFixing(date(2024, 1, 1))
+        );
+
+        let zero_roll_day = one_year_loan()
+            .with_repayment(RepaymentProfile::Bullet, Some(Frequency::Monthly))
+            .with_period_rules(PeriodRules {
+                roll_day: Some(0),
+                ..PeriodRules::default()
+            });
+        assert_eq!(
+            Schedule::new(&zero_roll_day).unwrap_err(),
+            LoanError::InvalidRollDay(0)
+        );
+
+        let long_lookback = one_year_loan().with_rfr(RfrTerms {
+            lookback_days: 5,
+            ..RfrTerms::default()
+        });
+        assert!(matches!(
+            Accruals::new(&long_lookback),
+            Err(LoanError::Rfr(_))
+        ));
+    }
+
+    // `Loan::total_interest` accrues runs of days at once, so its interest
+    // with and without margin, commitment fee and utilisation fee are
+    // compared with the sums over every day of `Schedule::new`, for every
+    // day count, compounding method, rounding stage and repayment profile.
+    // The loan crosses two year ends, a leap day and month ends of every
+    // length and has a rate change, a prepayment and fees, so each reason
+    // `run_end` has to cut a run short is exercised.
+    #[test]
+    fn total_interest_matches_the_schedule() {
+        let fixings = RateSchedule::new(vec![
+            Fixing {
+                effective_date: date(2023, 11, 1),
+                rate: Decimal::new(425, 2),
             },
-            without_margin: Money {
-                value: interest_without_margin,
-                code: currency_code,
+            Fixing {
+                effective_date: date(2024, 6, 17),
+                rate: Decimal::new(3875, 3),
             },
+        ]);
+        let facility = FacilityType::Revolving(RevolvingFacility::new(
+            Decimal::from(400_000),
+            CommitmentFee::MarginPercentage(Decimal::from(35)),
+            vec![UtilisationTier {
+                above_percentage: Decimal::from(50),
+                fee_rate: Decimal::new(15, 2),
+            }],
+        ));
+        let mut checked = 0;
+        for day_count in [
+            DayCountConvention::Act365Fixed,
+            DayCountConvention::Act360,
+            DayCountConvention::Act365Leap,
+            DayCountConvention::Thirty360Us,
+            DayCountConvention::ThirtyE360,
+            DayCountConvention::ThirtyE360Isda,
+            DayCountConvention::ActActIsda,
+            DayCountConvention::ActActIcma,
+        ] {
+            for compounding in [
+                CompoundingMethod::Simple,
+                CompoundingMethod::Daily,
+                CompoundingMethod::Monthly,
+                CompoundingMethod::Quarterly,
+                CompoundingMethod::Annual,
+                CompoundingMethod::Continuous,
+            ] {
+                // Rounding each day to 28 places keeps the sums at full
+                // precision, so they must match to the last digit too
+                for (stage, precision) in [
+                    (RoundingStage::Daily, None),
+                    (RoundingStage::Period, None),
+                    (RoundingStage::Total, None),
+                    (RoundingStage::Daily, Some(28)),
+                ] {
+                    for repayment in [
+                        RepaymentProfile::Bullet,
+                        RepaymentProfile::Annuity,
+                        RepaymentProfile::Linear,
+                        RepaymentProfile::Balloon {
+                            residual_percentage: Decimal::from(40),
+                        },
+                    ] {
+                        let loan = Loan::new(
+                            date(2023, 11, 15),
+                            date(2025, 3, 14),
+                            Decimal::new(25_000_000, 2),
+                            fixings.clone(),
+                            Decimal::new(175, 2),
+                            CurrencyCode::GBP,
+                        )
+                        .with_day_count(day_count)
+                        .with_compounding(compounding)
+                        .with_repayment(repayment, Some(Frequency::Monthly))
+                        .with_events(vec![LoanEvent {
+                            date: date(2024, 9, 10),
+                            kind: LoanEventKind::Prepayment(Decimal::from(10_000)),
+                        }])
+                        .with_facility(facility.clone())
+                        .with_rounding(RoundingPolicy {
+                            stage,
+                            precision,
+                            ..RoundingPolicy::default()
+                        });
+                        let case = format!(
+                            "{} {} {:?} {:?} {:?}",
+                            day_count, compounding, stage, precision, repayment
+                        );
+
+                        let schedule = Schedule::new(&loan)
+                            .unwrap()
+                            .calculate_interest()
+                            .unwrap()
+                            .unwrap();
+                        let closed_form = loan.total_interest().unwrap().unwrap();
+                        assert_eq!(closed_form.with_margin, schedule.with_margin, "{}", case);
+                        assert_eq!(
+                            closed_form.without_margin, schedule.without_margin,
+                            "{}",
+                            case
+                        );
+                        assert_eq!(
+                            closed_form.commitment_fee, schedule.commitment_fee,
+                            "{}",
+                            case
+                        );
+                        assert_eq!(
+                            closed_form.utilisation_fee, schedule.utilisation_fee,
+                            "{}",
+                            case
+                        );
+                        checked += 1;
+                    }
+                }
+            }
+        }
+        assert_eq!(checked, 768);
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::json;
+
+    use super::*;
+    use crate::event::LoanEventKind;
+    use crate::frequency::Frequency;
+    use crate::rate_schedule::Fixing;
+    use crate::rfr::ObservationMethod;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Serializes `value`, reads it back and checks it serializes the same
+    fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        let json = serde_json::to_string(value).unwrap();
+        let read: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), json);
+        read
+    }
+
+    // Three days to a monthly payment on 1 February 2024
+    fn loan() -> Loan {
+        Loan::new(
+            date(2024, 1, 30),
+            date(2024, 2, 1),
+            Decimal::from(1000),
+            RateSchedule::flat(Decimal::from(5)),
+            Decimal::ONE,
+            CurrencyCode::GBP,
+        )
+        .with_repayment(RepaymentProfile::Bullet, Some(Frequency::Monthly))
+    }
+
+    #[test]
+    fn schedules_round_trip() {
+        let schedule = Schedule::new(&loan()).unwrap();
+        let read = round_trip(&schedule);
+        assert_eq!(read.entries.len(), 3);
+        assert_eq!(read.rounding.stage, schedule.rounding.stage);
+
+        // Events and compounded rates are carried by the entries too
+        let fixings = (1..=9)
+            .map(|day| Fixing {
+                effective_date: date(2024, 1, day),
+                rate: Decimal::new(5190 + day as i64, 3),
+            })
+            .collect();
+        let rfr_loan = Loan::new(
+            date(2024, 1, 3),
+            date(2024, 1, 8),
+            Decimal::from(1000),
+            RateSchedule::new(fixings),
+            Decimal::ONE,
+            CurrencyCode::GBP,
+        )
+        .with_rfr(RfrTerms {
+            method: ObservationMethod::ObservationShift,
+            lookback_days: 2,
+            lockout_days: 1,
+            payment_delay_days: 2,
+            day_basis: 365,
         })
+        .with_events(vec![LoanEvent {
+            date: date(2024, 1, 5),
+            kind: LoanEventKind::Prepayment(Decimal::from(100)),
+        }]);
+        let read = round_trip(&Schedule::new(&rfr_loan).unwrap());
+        assert_eq!(read.entries[2].events.len(), 1);
+        assert!(read
+            .entries
+            .iter()
+            .all(|entry| entry.compounded_rate.is_some()));
+    }
+
+    #[test]
+    fn schedule_parts_round_trip() {
+        let schedule = Schedule::new(&loan()).unwrap();
+        round_trip(&schedule.entries[0]);
+        let payment = round_trip(&schedule.entries[2].payment.unwrap());
+        assert_eq!(payment.payment_date, date(2024, 2, 1));
+        let total_interest = round_trip(&schedule.calculate_interest().unwrap().unwrap());
+        assert_eq!(total_interest.with_margin.value, Decimal::new(48, 2));
+        let differences = round_trip(&schedule.rounding_differences().unwrap());
+        assert_eq!(differences.len(), 2);
+    }
+
+    #[test]
+    fn schedule_json_shape() {
+        let schedule = Schedule::new(&loan()).unwrap();
+        let gbp = |amount: &str| json!({ "amount": amount, "currency": "GBP" });
+        assert_eq!(
+            serde_json::to_value(&schedule.entries[0]).unwrap(),
+            json!({
+                "daily_interest_without_margin": gbp("0.1366120218579234972677596000"),
+                "daily_interest_with_margin": gbp("0.1639344262295081967213115000"),
+                "accrual_date": "2024-01-30",
+                "days_elapsed": 0,
+                "days_in_year": 366,
+                "raw_base_rate": "5",
+                "base_rate": "5",
+                "events": [],
+                "opening_balance": gbp("1000"),
+                "undrawn_balance": gbp("0"),
+                "commitment_fee": gbp("0"),
+                "utilisation_fee": gbp("0"),
+                "accrued_interest": gbp("0.16"),
+                "capitalised_interest": gbp("0"),
+                "compounded_rate": null,
+                "payment": null
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(schedule.entries[2].payment).unwrap(),
+            json!({
+                "period_end": "2024-02-01",
+                "payment_date": "2024-02-01",
+                "principal": gbp("1000"),
+                "interest": gbp("0.48"),
+                "fees": gbp("0"),
+                "outstanding_principal": gbp("0")
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(schedule.rounding).unwrap(),
+            json!({ "strategy": "half-even", "precision": null, "stage": "daily" })
+        );
+        assert_eq!(
+            serde_json::to_value(schedule.calculate_interest().unwrap()).unwrap(),
+            json!({
+                "with_margin": gbp("0.48"),
+                "without_margin": gbp("0.42"),
+                "commitment_fee": gbp("0"),
+                "utilisation_fee": gbp("0")
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(schedule.rounding_differences().unwrap()).unwrap(),
+            json!([
+                {
+                    "period_end": "2024-02-01",
+                    "unrounded": gbp("0.4918032786885245901639345000"),
+                    "rounded": gbp("0.48")
+                },
+                {
+                    "period_end": null,
+                    "unrounded": gbp("0.4918032786885245901639345000"),
+                    "rounded": gbp("0.48")
+                }
+            ])
+        );
+    }
+
+    #[test]
+    fn rfr_terms_without_a_day_basis_compound_on_365_days() {
+        let terms: RfrTerms = serde_json::from_value(json!({
+            "method": "lookback",
+            "lookback_days": 5,
+            "lockout_days": 0,
+            "payment_delay_days": 0
+        }))
+        .unwrap();
+        assert_eq!(terms.day_basis, 365);
+    }
+
+    #[test]
+    fn flat_rate_schedules_round_trip() {
+        let flat = RateSchedule::flat(Decimal::new(525, 2));
+        assert_eq!(serde_json::to_string(&flat).unwrap(), r#""5.25""#);
+        let read = round_trip(&flat);
+        assert_eq!(read.flat_rate(), Some(Decimal::new(525, 2)));
+        assert_eq!(read.rate_on(date(1900, 1, 1)), Some(Decimal::new(525, 2)));
+
+        // Rates written as numbers read as the same decimal
+        for (json, rate) in [("5", Decimal::from(5)), ("5.1", Decimal::new(51, 1))] {
+            let read: RateSchedule = serde_json::from_str(json).unwrap();
+            assert_eq!(read.flat_rate(), Some(rate), "{}", json);
+        }
+
+        // A single fixing from a date is not flat
+        let fixing: RateSchedule =
+            serde_json::from_str(r#"[{"effective_date":"2024-01-01","rate":"5"}]"#).unwrap();
+        assert_eq!(fixing.flat_rate(), None);
+        assert_eq!(fixing.rate_on(date(2023, 12, 31)), None);
+    }
+
+    #[test]
+    fn fixings_and_events_are_read_in_date_order() {
+        let loan: Loan = serde_json::from_value(json!({
+            "start_date": "2024-01-01",
+            "end_date": "2024-12-31",
+            "loan_amount": "1000",
+            "base_rate": [
+                { "effective_date": "2024-07-01", "rate": "4.5" },
+                { "effective_date": "2024-01-01", "rate": "5" }
+            ],
+            "margin": "1",
+            "currency": "GBP",
+            "events": [
+                { "date": "2024-09-01", "event": "repayment" },
+                { "date": "2024-03-01", "event": "prepayment", "amount": "100" }
+            ]
+        }))
+        .unwrap();
+        assert_eq!(
+            loan.base_rate.rate_on(date(2024, 3, 1)),
+            Some(Decimal::from(5))
+        );
+        assert_eq!(
+            loan.events
+                .iter()
+                .map(|event| event.date)
+                .collect::<Vec<_>>(),
+            [date(2024, 3, 1), date(2024, 9, 1)]
+        );
+
+        let read = round_trip(&loan);
+        assert_eq!(read.base_rate.fixings()[0].effective_date, date(2024, 1, 1));
+        assert_eq!(read.events[0].date, date(2024, 3, 1));
+    }
+}
+
+#[cfg(all(test, feature = "loan-file"))]
+mod loan_file_tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Writes `contents` to a file in the temporary directory, named after
+    // the process so that concurrent test runs do not share files
+    fn loan_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oneiro-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // One loan written in each format, with every kind of term given
+    const TOML_LOAN: &str = r#"start_date = "2024-01-01"
+end_date = "2024-12-31"
+loan_amount = "1000000"
+base_rate = "5.25"
+margin = "2.5"
+currency = "GBP"
+day_count = "ACT/365F"
+compounding = "monthly"
+payment_frequency = "quarterly"
+
+[repayment]
+type = "balloon"
+residual_percentage = "20"
+
+[[events]]
+date = "2024-06-03"
+event = "drawdown"
+amount = "100000"
+"#;
+
+    const YAML_LOAN: &str = r#"start_date: "2024-01-01"
+end_date: "2024-12-31"
+loan_amount: "1000000"
+base_rate: "5.25"
+margin: "2.5"
+currency: GBP
+day_count: ACT/365F
+compounding: monthly
+payment_frequency: quarterly
+repayment:
+  type: balloon
+  residual_percentage: "20"
+events:
+  - date: "2024-06-03"
+    event: drawdown
+    amount: "100000"
+"#;
+
+    const JSON_LOAN: &str = r#"{
+  "start_date": "2024-01-01",
+  "end_date": "2024-12-31",
+  "loan_amount": "1000000",
+  "base_rate": "5.25",
+  "margin": "2.5",
+  "currency": "GBP",
+  "day_count": "ACT/365F",
+  "compounding": "monthly",
+  "payment_frequency": "quarterly",
+  "repayment": {"type": "balloon", "residual_percentage": "20"},
+  "events": [{"date": "2024-06-03", "event": "drawdown", "amount": "100000"}]
+}"#;
+
+    #[test]
+    fn toml_yaml_and_json_files_read_the_same_loan() {
+        let toml = Loan::from_file(&loan_file("loan.toml", TOML_LOAN)).unwrap();
+        let yaml = Loan::from_file(&loan_file("loan.yaml", YAML_LOAN)).unwrap();
+        let yml = Loan::from_file(&loan_file("loan.yml", YAML_LOAN)).unwrap();
+        let json = Loan::from_file(&loan_file("loan.json", JSON_LOAN)).unwrap();
+        assert_eq!(format!("{:?}", yaml), format!("{:?}", toml));
+        assert_eq!(format!("{:?}", yml), format!("{:?}", toml));
+        assert_eq!(format!("{:?}", json), format!("{:?}", toml));
+
+        assert_eq!(toml.base_rate.flat_rate(), Some(Decimal::new(525, 2)));
+        assert_eq!(toml.day_count, DayCountConvention::Act365Fixed);
+        assert_eq!(toml.compounding, CompoundingMethod::Monthly);
+        assert_eq!(toml.payment_frequency, Some(Frequency::Quarterly));
+        assert_eq!(
+            toml.repayment,
+            RepaymentProfile::Balloon {
+                residual_percentage: Decimal::from(20)
+            }
+        );
+        assert_eq!(toml.events.len(), 1);
+        assert!(toml.validate(&RateLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn terms_left_out_take_the_defaults_of_new_loans() {
+        let path = loan_file(
+            "minimal.yaml",
+            "start_date: 2024-01-01\nend_date: 2024-12-31\nloan_amount: 1000\n\
+             base_rate: 5\nmargin: 1\ncurrency: EUR\n",
+        );
+        let loan = Loan::from_file(&path).unwrap();
+        let new = Loan::new(
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            Decimal::from(1_000),
+            RateSchedule::flat(Decimal::from(5)),
+            Decimal::from(1),
+            CurrencyCode::EUR,
+        );
+        assert_eq!(format!("{:?}", loan), format!("{:?}", new));
+    }
+
+    #[test]
+    fn the_example_loan_file_is_valid() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/loan.toml");
+        let loan = Loan::from_file(&path).unwrap();
+        assert_eq!(loan.base_rate.fixings().len(), 2);
+        assert_eq!(loan.rate_bounds.floor, Some(Decimal::ZERO));
+        assert!(loan.validate(&RateLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn errors_name_the_line_of_the_file() {
+        let yaml = YAML_LOAN.replace("margin: \"2.5\"", "margin: high");
+        let error = Loan::from_file(&loan_file("bad-margin.yaml", &yaml)).unwrap_err();
+        assert_eq!(error.line(), 5);
+        assert!(error
+            .to_string()
+            .starts_with("Error reading loan file on line 5: "));
+
+        let toml = TOML_LOAN.replace("compounding = \"monthly\"", "compounding = \"hourly\"");
+        let error = Loan::from_file(&loan_file("bad-compounding.toml", &toml)).unwrap_err();
+        assert_eq!(error.line(), 8);
+    }
+
+    #[test]
+    fn files_without_a_known_extension_are_errors() {
+        let error = Loan::from_file(&loan_file("loan.ini", TOML_LOAN)).unwrap_err();
+        assert_eq!(error.line(), 0);
+        assert!(error
+            .to_string()
+            .contains("expected a .toml, .yaml, .yml or .json file"));
+    }
+
+    #[test]
+    fn unknown_currencies_are_reported_with_their_code() {
+        let path = loan_file(
+            "unknown-currency.toml",
+            r#"start_date = "2024-01-01"
+end_date = "2024-02-01"
+loan_amount = "1000"
+base_rate = [{ effective_date = "2024-01-01", rate = "5" }]
+margin = "1"
+currency = "ABC"
+"#,
+        );
+        let error = Loan::from_file(&path).unwrap_err();
+        assert_eq!(error.line(), 6);
+        assert_eq!(
+            error.unknown_currency().unwrap().to_string(),
+            "Error unknown currency code: ABC"
+        );
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn other_errors_have_no_unknown_currency() {
+        let path = loan_file(
+            "bad-margin.json",
+            r#"{"start_date": "2024-01-01", "end_date": "2024-02-01",
+"loan_amount": "1000", "margin": "one", "currency": "GBP",
+"base_rate": [{"effective_date": "2024-01-01", "rate": "5"}]}"#,
+        );
+        let error = Loan::from_file(&path).unwrap_err();
+        assert_eq!(error.line(), 2);
+        assert!(error.unknown_currency().is_none());
     }
 }
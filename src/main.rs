@@ -1,121 +1,1544 @@
-mod loan;
-
-use loan::CurrencyCode;
-use loan::Loan;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
 
 use chrono::NaiveDate;
-use clap::Parser;
-use loan::Schedule;
-use prettytable::Table;
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::{CommandFactory, Parser, Subcommand};
+use log::LevelFilter;
+use oneiro::calendar::{BusinessDayConvention, HolidayCalendar};
+use oneiro::compounding::CompoundingMethod;
+use oneiro::currency::CurrencyCode;
+use oneiro::day_count::DayCountConvention;
+use oneiro::event::LoanEvent;
+use oneiro::facility::{CommitmentFee, FacilityType, UtilisationTier};
+use oneiro::frequency::Frequency;
+use oneiro::fx::FxRates;
+use oneiro::period::StubType;
+use oneiro::portfolio::{LoanSummary, Portfolio, PortfolioRow};
+use oneiro::rate_bounds::FloorBasis;
+use oneiro::repayment::RepaymentProfile;
+use oneiro::rfr::ObservationMethod;
+use oneiro::rounding::{RoundingStage, RoundingStrategy};
+use oneiro::{
+    CurrencyMismatchError, Loan, LoanError, LoanOverrides, Money, OverrideError, RateLimits,
+    RateSchedule, Schedule, TotalInterest,
+};
+use prettytable::{Cell, Row, Table};
 use rust_decimal::Decimal;
-
-#[macro_use]
-extern crate prettytable;
-
-/// Custom validator for currency format (e.g., USD, EUR, etc.)
-fn validate_currency_format(value: &str) -> Result<String, String> {
-    if value.chars().all(|c| c.is_ascii_uppercase()) && value.len() >= 3 && value.len() <= 5 {
-        Ok(value.into())
-    } else {
-        Err("Invalid currency format. Please use uppercase letters (e.g., USD, EUR).".to_string())
-    }
-}
+use serde_json::{Map, Value};
 
 /// Custom validator for date format (YYYY-MM-DD)
 fn validate_date_format(value: &str) -> Result<NaiveDate, String> {
-    if let Ok(date) = NaiveDate::parse_from_str(&value, "%Y-%m-%d") {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
         Ok(date)
     } else {
         Err("Invalid date format. Please use the format YYYY-MM-DD.".to_string())
     }
 }
 
+/// Custom validator for day count conventions (e.g., ACT/360, 30E/360)
+fn validate_day_count(value: &str) -> Result<DayCountConvention, String> {
+    DayCountConvention::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for compounding methods (e.g., simple, monthly)
+fn validate_compounding(value: &str) -> Result<CompoundingMethod, String> {
+    CompoundingMethod::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for floor basis (base or all-in)
+fn validate_floor_basis(value: &str) -> Result<FloorBasis, String> {
+    FloorBasis::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for payment frequencies (e.g., monthly, quarterly)
+fn validate_frequency(value: &str) -> Result<Frequency, String> {
+    Frequency::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for utilisation fee tiers (e.g., 33:0.10)
+fn validate_utilisation_tier(value: &str) -> Result<UtilisationTier, String> {
+    UtilisationTier::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for business day conventions (e.g., modified-following)
+fn validate_business_day_convention(value: &str) -> Result<BusinessDayConvention, String> {
+    BusinessDayConvention::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for stub types (e.g., short-front, long-back)
+fn validate_stub(value: &str) -> Result<StubType, String> {
+    StubType::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for rounding strategies (e.g., half-even, truncate)
+fn validate_rounding(value: &str) -> Result<RoundingStrategy, String> {
+    RoundingStrategy::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for rounding stages (daily, period, total)
+fn validate_rounding_stage(value: &str) -> Result<RoundingStage, String> {
+    RoundingStage::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for ISO 4217 currencies (e.g., GBP, JPY, 826)
+fn validate_currency(value: &str) -> Result<CurrencyCode, String> {
+    CurrencyCode::try_from(value).map_err(|e| e.to_string())
+}
+
+/// Custom validator for log levels (off, error, warn, info, debug, trace)
+fn validate_log_level(value: &str) -> Result<LevelFilter, String> {
+    value
+        .parse::<LevelFilter>()
+        .map_err(|_| format!("Error unknown log level: {}", value))
+}
+
+/// Custom validator for output formats (table, csv, json, jsonl)
+fn validate_format(value: &str) -> Result<OutputFormat, String> {
+    match value.to_lowercase().as_str() {
+        "table" => Ok(OutputFormat::Table),
+        "csv" => Ok(OutputFormat::Csv),
+        "json" => Ok(OutputFormat::Json),
+        "jsonl" | "ndjson" => Ok(OutputFormat::Jsonl),
+        _ => Err(format!("Error unknown output format: {}", value)),
+    }
+}
+
+/// Exit code for each way a loan can be invalid, other errors exit with 1
+/// and invalid arguments with 2
+fn exit_code(error: &LoanError) -> i32 {
+    match error {
+        LoanError::InvertedDates { .. } => 3,
+        LoanError::NonPositivePrincipal(_) => 4,
+        LoanError::RateOutOfBounds { .. } => 5,
+        LoanError::UnsupportedCurrency(_) => 6,
+        LoanError::MissingFixing(_) => 7,
+        LoanError::Rfr(_) | LoanError::RfrDayBasisMismatch { .. } => 8,
+        LoanError::FloorAboveCap { .. } => 9,
+        LoanError::BalloonResidualOutOfRange(_) => 10,
+        LoanError::EventOutsideTerm(_) => 11,
+        LoanError::AboveFacilityLimit { .. } => 12,
+        LoanError::InvalidRollDay(_) => 13,
+    }
+}
+
+/// Inserts `values` as consecutive cells starting at `index`
+fn insert_cells<T>(row: &mut Vec<T>, index: usize, values: impl IntoIterator<Item = T>) {
+    for (offset, value) in values.into_iter().enumerate() {
+        row.insert(index + offset, value);
+    }
+}
+
+/// How the schedule is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    /// Tables of the schedule, periods and rounding report
+    Table,
+    /// The daily schedule and its total as CSV
+    Csv,
+    /// One JSON document with the schedule, total, periods and rounding report
+    Json,
+    /// One JSON object per line for each day of the schedule, then the total
+    Jsonl,
+}
+
+/// A cell of the reported tables, shown as text in tables and kept as data
+/// in CSV and JSON
+enum Field {
+    Text(String),
+    Count(u64),
+    Rate(Decimal),
+    Amount(Money),
+    Empty,
+}
+
+impl Field {
+    fn text(value: impl ToString) -> Self {
+        Field::Text(value.to_string())
+    }
+
+    fn cell(&self) -> Cell {
+        match self {
+            Field::Text(text) => Cell::new(text),
+            Field::Count(count) => Cell::new(&count.to_string()),
+            Field::Rate(rate) => Cell::new(&rate.round_dp(6).to_string()),
+            Field::Amount(money) => Cell::new(&money.to_string()),
+            Field::Empty => Cell::new(""),
+        }
+    }
+
+    // Decimals are written as strings so no precision is lost. Rounded
+    // amounts match the table, rounded to the currency's minor units.
+    fn value(&self, unrounded: bool) -> Value {
+        match self {
+            Field::Text(text) => Value::String(text.clone()),
+            Field::Count(count) => Value::from(*count),
+            Field::Rate(rate) if unrounded => Value::String(rate.to_string()),
+            Field::Rate(rate) => Value::String(rate.round_dp(6).to_string()),
+            Field::Amount(money) if unrounded => Value::String(money.value.to_string()),
+            Field::Amount(money) => Value::String(money.rounded().to_string()),
+            Field::Empty => Value::Null,
+        }
+    }
+
+    fn csv_value(&self, unrounded: bool) -> String {
+        match self.value(unrounded) {
+            Value::String(text) => text,
+            Value::Null => String::new(),
+            value => value.to_string(),
+        }
+    }
+}
+
+/// Header, rows and optional total row of one of the reported tables
+struct Report {
+    header: Vec<&'static str>,
+    rows: Vec<Vec<Field>>,
+    total: Option<Vec<Field>>,
+}
+
+impl Report {
+    fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            self.header.iter().map(|title| Cell::new(title)).collect(),
+        ));
+        for row in self.rows.iter().chain(&self.total) {
+            table.add_row(Row::new(row.iter().map(Field::cell).collect()));
+        }
+        table
+    }
+
+    // CSV columns and JSON keys, e.g. accrual_date for Accrual Date
+    fn keys(&self) -> Vec<String> {
+        self.header
+            .iter()
+            .map(|title| title.to_lowercase().replace(' ', "_"))
+            .collect()
+    }
+
+    fn record(&self, row: &[Field], unrounded: bool) -> Value {
+        Value::Object(
+            self.keys()
+                .into_iter()
+                .zip(row.iter().map(|field| field.value(unrounded)))
+                .collect(),
+        )
+    }
+
+    fn records(&self, unrounded: bool) -> Vec<Value> {
+        self.rows
+            .iter()
+            .map(|row| self.record(row, unrounded))
+            .collect()
+    }
+
+    // Rows followed by the total
+    fn all_records(&self, unrounded: bool) -> Vec<Value> {
+        self.rows
+            .iter()
+            .chain(&self.total)
+            .map(|row| self.record(row, unrounded))
+            .collect()
+    }
+
+    /// Writes the rows and total alone in `format`, JSON as an array of rows
+    fn write(&self, out: &mut dyn Write, format: OutputFormat, unrounded: bool) -> io::Result<()> {
+        match format {
+            OutputFormat::Table => {
+                self.table().print(out)?;
+            }
+            OutputFormat::Csv => self.write_csv(out, unrounded)?,
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *out, &self.all_records(unrounded))?;
+                writeln!(out)?;
+            }
+            OutputFormat::Jsonl => {
+                for record in self.all_records(unrounded) {
+                    serde_json::to_writer(&mut *out, &record)?;
+                    writeln!(out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn write_csv(&self, out: &mut dyn Write, unrounded: bool) -> csv::Result<()> {
+        let mut writer = csv::Writer::from_writer(out);
+        writer.write_record(self.keys())?;
+        for row in self.rows.iter().chain(&self.total) {
+            writer.write_record(row.iter().map(|field| field.csv_value(unrounded)))?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes the schedule, its periods and the optional rounding report in
+/// `format`. CSV and JSON Lines only carry the schedule and its total.
+fn write_reports(
+    out: &mut dyn Write,
+    format: OutputFormat,
+    unrounded: bool,
+    schedule: &Report,
+    periods: &Report,
+    rounding: Option<&Report>,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Table => {
+            schedule.table().print(out)?;
+            periods.table().print(out)?;
+            if let Some(rounding) = rounding {
+                rounding.table().print(out)?;
+            }
+        }
+        OutputFormat::Csv | OutputFormat::Jsonl => schedule.write(out, format, unrounded)?,
+        OutputFormat::Json => {
+            let mut report = Map::new();
+            report.insert("schedule".into(), schedule.records(unrounded).into());
+            if let Some(total) = &schedule.total {
+                report.insert("total".into(), schedule.record(total, unrounded));
+            }
+            report.insert("periods".into(), periods.records(unrounded).into());
+            if let Some(rounding) = rounding {
+                report.insert(
+                    "rounding_differences".into(),
+                    rounding.records(unrounded).into(),
+                );
+            }
+            serde_json::to_writer_pretty(&mut *out, &report)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// A schedule converted into a reporting currency
+struct Reporting {
+    currency: CurrencyCode,
+    fx_rates: FxRates,
+    schedule: Schedule,
+    total_interest: TotalInterest,
+}
+
+/// Daily rows of `schedule` with `total_interest` as the total row
+fn schedule_report(
+    loan: &Loan,
+    schedule: &Schedule,
+    total_interest: &TotalInterest,
+    reporting: Option<&Reporting>,
+) -> Report {
+    let revolving = matches!(loan.facility, FacilityType::Revolving(_));
+
+    // RFR loans show how each day's rate was compounded after the base rate,
+    // floored or capped loans show the rate before the bounds applied and
+    // loans with events show them before the balance they changed. Columns
+    // are inserted from the right so the indexes stay valid. Loans reported in
+    // another currency gain the converted amounts after all other columns.
+    let raw_rate_column = 3;
+    let rfr_column = 4;
+    let events_column = 4;
+    let fees_column = 7;
+    let mut header = vec![
+        "Accrual Date",
+        "Days Elapsed",
+        "Days In Year",
+        "Base Rate",
+        "Opening Balance",
+        "Interest Without Margin",
+        "Interest With Margin",
+        "Accrued Interest",
+        "Capitalised Interest",
+        "Currency",
+    ];
+    if reporting.is_some() {
+        let last_column = header.len();
+        insert_cells(
+            &mut header,
+            last_column,
+            [
+                "FX Rate",
+                "Reporting Balance",
+                "Reporting Interest",
+                "Reporting Currency",
+            ],
+        );
+    }
+    if revolving {
+        insert_cells(
+            &mut header,
+            fees_column,
+            ["Undrawn", "Commitment Fee", "Utilisation Fee"],
+        );
+    }
+    if !loan.events.is_empty() {
+        insert_cells(&mut header, events_column, ["Events"]);
+    }
+    if loan.rfr.is_some() {
+        insert_cells(
+            &mut header,
+            rfr_column,
+            [
+                "Observation Date",
+                "Overnight Rate",
+                "Compounded Rate",
+                "Annualised Rate",
+            ],
+        );
+    }
+    if loan.rate_bounds.is_bounded() {
+        insert_cells(&mut header, raw_rate_column, ["Raw Base Rate"]);
+    }
+
+    let rows = schedule
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let mut row = vec![
+                Field::text(entry.accrual_date),
+                Field::Count(entry.days_elapsed),
+                Field::Count(entry.days_in_year.into()),
+                Field::Rate(entry.base_rate),
+                Field::Amount(entry.opening_balance),
+                Field::Amount(entry.daily_interest_without_margin),
+                Field::Amount(entry.daily_interest_with_margin),
+                Field::Amount(entry.accrued_interest),
+                Field::Amount(entry.capitalised_interest),
+                Field::text(loan.currency),
+            ];
+            if let Some(reporting) = reporting {
+                let converted = &reporting.schedule.entries[index];
+                let fx_rate = reporting
+                    .fx_rates
+                    .rate_on(loan.currency, reporting.currency, entry.accrual_date)
+                    .unwrap_or_default();
+                let last_column = row.len();
+                insert_cells(
+                    &mut row,
+                    last_column,
+                    [
+                        Field::Rate(fx_rate),
+                        Field::Amount(converted.opening_balance),
+                        Field::Amount(converted.daily_interest_with_margin),
+                        Field::text(reporting.currency),
+                    ],
+                );
+            }
+            if revolving {
+                insert_cells(
+                    &mut row,
+                    fees_column,
+                    [
+                        Field::Amount(entry.undrawn_balance),
+                        Field::Amount(entry.commitment_fee),
+                        Field::Amount(entry.utilisation_fee),
+                    ],
+                );
+            }
+            if !loan.events.is_empty() {
+                let events = entry
+                    .events
+                    .iter()
+                    .map(|event| event.to_string())
+                    .collect::<Vec<_>>();
+                insert_cells(&mut row, events_column, [Field::text(events.join("\n"))]);
+            }
+            if let Some(rate) = entry.compounded_rate {
+                insert_cells(
+                    &mut row,
+                    rfr_column,
+                    [
+                        Field::text(rate.observation_date),
+                        Field::text(rate.overnight_rate),
+                        Field::Rate(rate.cumulative_rate),
+                        Field::Rate(rate.annualised_rate),
+                    ],
+                );
+            }
+            if loan.rate_bounds.is_bounded() {
+                insert_cells(
+                    &mut row,
+                    raw_rate_column,
+                    [Field::Rate(entry.raw_base_rate)],
+                );
+            }
+            row
+        })
+        .collect();
+
+    let mut total = vec![
+        Field::text("Total"),
+        Field::Empty,
+        Field::Empty,
+        Field::Empty,
+        Field::Empty,
+        Field::Amount(total_interest.without_margin),
+        Field::Amount(total_interest.with_margin),
+        Field::Empty,
+        Field::Empty,
+        Field::text(loan.currency),
+    ];
+    if let Some(reporting) = reporting {
+        let last_column = total.len();
+        insert_cells(
+            &mut total,
+            last_column,
+            [
+                Field::Empty,
+                Field::Empty,
+                Field::Amount(reporting.total_interest.with_margin),
+                Field::text(reporting.currency),
+            ],
+        );
+    }
+    if revolving {
+        insert_cells(
+            &mut total,
+            fees_column,
+            [
+                Field::Empty,
+                Field::Amount(total_interest.commitment_fee),
+                Field::Amount(total_interest.utilisation_fee),
+            ],
+        );
+    }
+    if !loan.events.is_empty() {
+        insert_cells(&mut total, events_column, [Field::Empty]);
+    }
+    if loan.rfr.is_some() {
+        insert_cells(
+            &mut total,
+            rfr_column,
+            [Field::Empty, Field::Empty, Field::Empty, Field::Empty],
+        );
+    }
+    if loan.rate_bounds.is_bounded() {
+        insert_cells(&mut total, raw_rate_column, [Field::Empty]);
+    }
+
+    Report {
+        header,
+        rows,
+        total: Some(total),
+    }
+}
+
+/// One row per interest period of `schedule`
+fn periods_report(loan: &Loan, schedule: &Schedule) -> Result<Report, CurrencyMismatchError> {
+    let revolving = matches!(loan.facility, FacilityType::Revolving(_));
+    let period_fees_column = 6;
+    let mut header = vec![
+        "Period Start",
+        "Period End",
+        "Days",
+        "Payment Date",
+        "Principal",
+        "Interest Due",
+        "Total Payment",
+        "Outstanding Principal",
+        "Currency",
+    ];
+    if revolving {
+        insert_cells(&mut header, period_fees_column, ["Fees"]);
+    }
+
+    let rows = schedule
+        .periods()
+        .iter()
+        .map(|period| {
+            let payment = period.payment;
+            let mut row = vec![
+                Field::text(period.start_date()),
+                Field::text(period.end_date()),
+                Field::Count(period.days() as u64),
+                Field::text(period.payment_date()),
+                Field::Amount(payment.principal),
+                Field::Amount(period.interest_due()),
+                Field::Amount(payment.total()?),
+                Field::Amount(payment.outstanding_principal),
+                Field::text(loan.currency),
+            ];
+            if revolving {
+                insert_cells(&mut row, period_fees_column, [Field::Amount(payment.fees)]);
+            }
+            Ok(row)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Report {
+        header,
+        rows,
+        total: None,
+    })
+}
+
+/// One row per loan of the portfolio with its totals, or the error that
+/// stopped it
+fn summary_report(portfolio: &Portfolio, summaries: &[LoanSummary]) -> Report {
+    let rows = portfolio
+        .rows
+        .iter()
+        .zip(summaries)
+        .map(|(row, summary)| {
+            let loan = row.loan.as_ref().ok();
+            let mut fields = vec![
+                Field::text(&summary.id),
+                Field::Count(summary.line),
+                loan.map_or(Field::Empty, |loan| Field::text(loan.start_date)),
+                loan.map_or(Field::Empty, |loan| Field::text(loan.end_date)),
+                loan.map_or(Field::Empty, |loan| {
+                    Field::Amount(Money::new(loan.loan_amount, loan.currency))
+                }),
+            ];
+            match &summary.total_interest {
+                Ok(total_interest) => fields.extend([
+                    Field::Amount(total_interest.without_margin),
+                    Field::Amount(total_interest.with_margin),
+                    Field::Amount(total_interest.commitment_fee),
+                    Field::Amount(total_interest.utilisation_fee),
+                    Field::text(total_interest.with_margin.code),
+                    Field::text("ok"),
+                    Field::Empty,
+                ]),
+                Err(e) => fields.extend([
+                    Field::Empty,
+                    Field::Empty,
+                    Field::Empty,
+                    Field::Empty,
+                    loan.map_or(Field::Empty, |loan| Field::text(loan.currency)),
+                    Field::text("error"),
+                    Field::text(e),
+                ]),
+            }
+            fields
+        })
+        .collect();
+    Report {
+        header: vec![
+            "Id",
+            "Line",
+            "Start Date",
+            "End Date",
+            "Loan Amount",
+            "Interest Without Margin",
+            "Interest With Margin",
+            "Commitment Fee",
+            "Utilisation Fee",
+            "Currency",
+            "Status",
+            "Error",
+        ],
+        rows,
+        total: None,
+    }
+}
+
+/// Writes the schedule and periods of a portfolio loan into `dir`, named
+/// after the loan's line in the portfolio file and its id
+fn write_schedule(
+    dir: &Path,
+    format: OutputFormat,
+    unrounded: bool,
+    row: &PortfolioRow,
+    schedule: &Schedule,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let loan = row.loan.as_ref().map_err(Clone::clone)?;
+    let total_interest = schedule.calculate_interest()?.ok_or("no days to accrue")?;
+    let schedule_report = schedule_report(loan, schedule, &total_interest, None);
+    let periods_report = periods_report(loan, schedule)?;
+
+    // Ids can hold any text, file names keep to characters safe everywhere.
+    // The line keeps loans with the same id, or ids that only differ in
+    // unsafe characters, from writing the same file.
+    let name = row
+        .id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let extension = match format {
+        OutputFormat::Table => "txt",
+        OutputFormat::Csv => "csv",
+        OutputFormat::Json => "json",
+        OutputFormat::Jsonl => "jsonl",
+    };
+    let mut file = BufWriter::new(File::create(
+        dir.join(format!("{}-{}.{}", row.line, name, extension)),
+    )?);
+    write_reports(
+        &mut file,
+        format,
+        unrounded,
+        &schedule_report,
+        &periods_report,
+        None,
+    )?;
+    file.flush()?;
+    Ok(())
+}
+
+/// Calculates every loan of a portfolio file and writes the summary. Loans
+/// that fail are reported in the summary and on stderr without stopping the
+/// others, and make the run exit with 1 once everything is written.
+fn run_batch(batch: BatchArgs) {
+    let portfolio = match Portfolio::from_file(&batch.portfolio) {
+        Ok(portfolio) => match batch.threads {
+            Some(threads) => portfolio.with_threads(threads.into()),
+            None => portfolio,
+        },
+        Err(e) => {
+            eprintln!("Error invalid portfolio file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let Some(dir) = &batch.schedules_dir {
+        if let Err(e) = fs::create_dir_all(dir) {
+            eprintln!("Error invalid schedules directory: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let rate_limits = RateLimits {
+        min: batch.min_rate,
+        max: batch.max_rate,
+    };
+    // Only loans whose schedules are written out need them built
+    let summaries = match &batch.schedules_dir {
+        Some(dir) => portfolio.calculate(&rate_limits, |row, schedule| {
+            write_schedule(dir, batch.schedule_format, batch.unrounded, row, schedule)
+        }),
+        None => portfolio.total_interest(&rate_limits),
+    };
+    for summary in &summaries {
+        if let Err(e) = &summary.total_interest {
+            eprintln!("Error invalid loan {}: {}", summary.id, e);
+        }
+    }
+
+    let mut output = open_output(batch.output.as_deref());
+    let written = summary_report(&portfolio, &summaries)
+        .write(&mut output, batch.format, batch.unrounded)
+        .and_then(|()| output.flush());
+    if let Err(e) = written {
+        eprintln!("Error writing output: {}", e);
+        std::process::exit(1);
+    }
+    if summaries
+        .iter()
+        .any(|summary| summary.total_interest.is_err())
+    {
+        std::process::exit(1);
+    }
+}
+
+/// A buffered file at `path`, or stdout without one
+fn open_output(path: Option<&Path>) -> Box<dyn Write> {
+    match path {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(e) => {
+                eprintln!("Error invalid output file: {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => Box::new(io::stdout().lock()),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Calculate every loan of a CSV or JSON Lines portfolio and write one
+    /// summary row per loan
+    Batch(BatchArgs),
+}
+
+#[derive(clap::Args, Debug)]
+struct BatchArgs {
+    /// CSV with id,start_date,end_date,loan_amount,currency,base_rate,margin
+    /// and optional day_count, compounding, repayment, balloon_residual,
+    /// payment_frequency, floor, cap and business_day_convention columns, or
+    /// JSON Lines with one loan per line
+    portfolio: PathBuf,
+
+    /// Summary format (table, csv, json, jsonl)
+    #[arg(long, default_value = "csv", value_parser = validate_format)]
+    format: OutputFormat,
+
+    /// File to write the summary to instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+
+    /// Directory to write each loan's schedule to, named after its id
+    #[arg(long)]
+    schedules_dir: Option<PathBuf>,
+
+    /// Format of the schedule files (table, csv, json, jsonl)
+    #[arg(long, default_value = "csv", value_parser = validate_format)]
+    schedule_format: OutputFormat,
+
+    /// Write amounts and rates at full precision in the csv, json and jsonl
+    /// formats, rather than rounded as in the table
+    #[arg(long)]
+    unrounded: bool,
+
+    /// Lowest all-in rate (base rate plus margin) accepted, as a percentage
+    #[arg(long, default_value = "-10", allow_negative_numbers = true)]
+    min_rate: Decimal,
+
+    /// Highest all-in rate (base rate plus margin) accepted, as a percentage
+    #[arg(long, default_value = "100", allow_negative_numbers = true)]
+    max_rate: Decimal,
+
+    /// Threads to calculate loans on, one per CPU by default
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..))]
+    threads: Option<u16>,
+}
+
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// TOML, YAML or JSON file describing the loan, the other loan flags
+    /// override its fields
+    #[arg(long)]
+    loan_file: Option<PathBuf>,
+
     /// Start Date (format: YYYY-MM-DD)
-    #[arg(long, value_parser = validate_date_format)]
-    start_date: NaiveDate,
+    #[arg(
+        long,
+        required_unless_present = "loan_file",
+        value_parser = validate_date_format
+    )]
+    start_date: Option<NaiveDate>,
 
     /// End Date (format: YYYY-MM-DD)
-    #[arg(long, value_parser = validate_date_format)]
-    end_date: NaiveDate,
+    #[arg(
+        long,
+        required_unless_present = "loan_file",
+        value_parser = validate_date_format
+    )]
+    end_date: Option<NaiveDate>,
 
     /// Loan Amount
-    #[arg(long)]
-    loan_amount: Decimal,
+    #[arg(
+        long,
+        required_unless_present = "loan_file",
+        allow_negative_numbers = true
+    )]
+    loan_amount: Option<Decimal>,
 
     /// Loan Currency
-    #[arg(long, value_parser = validate_currency_format)]
-    loan_currency: String,
+    #[arg(
+        long,
+        required_unless_present = "loan_file",
+        value_parser = validate_currency
+    )]
+    loan_currency: Option<CurrencyCode>,
 
     /// Base Interest Rate
-    #[arg(long)]
-    base_interest_rate: Decimal,
+    #[arg(
+        long,
+        required_unless_present_any = ["base_rate_file", "loan_file"],
+        allow_negative_numbers = true
+    )]
+    base_interest_rate: Option<Decimal>,
+
+    /// CSV of base rate fixings with effective_date,rate columns
+    #[arg(long, conflicts_with = "base_interest_rate")]
+    base_rate_file: Option<PathBuf>,
 
     /// Margin Interest Rate
+    #[arg(long, required_unless_present = "loan_file")]
+    margin: Option<Decimal>,
+
+    /// Day Count Convention (ACT/365F, ACT/360, ACT/365L, 30/360US, 30E/360,
+    /// 30E/360ISDA, ACT/ACT-ISDA, ACT/ACT-ICMA), ACT/ACT-ISDA by default
+    #[arg(long, value_parser = validate_day_count)]
+    day_count: Option<DayCountConvention>,
+
+    /// Compounding Method (simple, daily, monthly, quarterly, annual,
+    /// continuous), simple by default
+    #[arg(long, value_parser = validate_compounding)]
+    compounding: Option<CompoundingMethod>,
+
+    /// Compound the base rate file in arrears as overnight RFR fixings
+    #[arg(long, requires = "base_rate_file")]
+    rfr: bool,
+
+    /// Business days to look back when observing RFR fixings, 0 by default
+    #[arg(long)]
+    lookback_days: Option<usize>,
+
+    /// Shift the RFR observation period, and its weights, by the lookback
+    #[arg(long)]
+    observation_shift: bool,
+
+    /// Business days at the end of the period that reuse the prior RFR
+    /// fixing, 0 by default
+    #[arg(long)]
+    lockout_days: Option<usize>,
+
+    /// Business days between the end of the period and payment, 0 by default
+    #[arg(long)]
+    payment_delay_days: Option<usize>,
+
+    /// Days in the year RFR fixings are quoted on, 365 (SONIA) by default or
+    /// 360 for SOFR and €STR
+    #[arg(long)]
+    rfr_day_basis: Option<u32>,
+
+    /// Minimum Base Interest Rate
+    #[arg(long, allow_negative_numbers = true)]
+    floor: Option<Decimal>,
+
+    /// Maximum Base Interest Rate
+    #[arg(long, allow_negative_numbers = true)]
+    cap: Option<Decimal>,
+
+    /// Whether the floor applies to the base rate or base rate plus margin
+    /// (base, all-in), base by default
+    #[arg(long, value_parser = validate_floor_basis)]
+    floor_basis: Option<FloorBasis>,
+
+    /// Repayment Profile (bullet, annuity, linear, balloon), bullet by default
+    #[arg(long)]
+    repayment: Option<String>,
+
+    /// Balloon residual as a percentage of the loan amount
+    #[arg(long, required_if_eq("repayment", "balloon"))]
+    balloon_residual: Option<Decimal>,
+
+    /// Payment Frequency (monthly, quarterly, semi-annual, annual), everything
+    /// is paid on the end date when not given
+    #[arg(long, value_parser = validate_frequency)]
+    payment_frequency: Option<Frequency>,
+
+    /// Stub Period (short-front, long-front, short-back, long-back),
+    /// short-back by default
+    #[arg(long, value_parser = validate_stub)]
+    stub: Option<StubType>,
+
+    /// Day of the month regular periods end on
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=31))]
+    roll_day: Option<u32>,
+
+    /// Roll periods on month ends when rolling from a month end
+    #[arg(long)]
+    end_of_month: bool,
+
+    /// CSV of prepayments and drawdowns with date,event,amount columns
+    #[arg(long)]
+    events: Option<PathBuf>,
+
+    /// Committed limit, makes the loan a revolving credit facility drawn at the
+    /// loan amount
+    #[arg(long)]
+    facility_limit: Option<Decimal>,
+
+    /// Commitment Fee Rate on the undrawn balance
+    #[arg(long)]
+    commitment_fee: Option<Decimal>,
+
+    /// Commitment Fee as a percentage of the margin
+    #[arg(long, conflicts_with = "commitment_fee")]
+    commitment_fee_margin_percentage: Option<Decimal>,
+
+    /// Utilisation Fee tiers as <above percentage>:<fee rate> (e.g., 33:0.10,66:0.20)
+    #[arg(long, value_delimiter = ',', value_parser = validate_utilisation_tier)]
+    utilisation_fees: Vec<UtilisationTier>,
+
+    /// Holiday file with one YYYY-MM-DD date per line, repeat to combine
+    /// calendars
+    #[arg(long = "calendar")]
+    calendars: Vec<PathBuf>,
+
+    /// Business Day Convention for period ends and payment dates (following,
+    /// modified-following, preceding, modified-preceding, unadjusted),
+    /// unadjusted by default
+    #[arg(long, value_parser = validate_business_day_convention)]
+    business_day_convention: Option<BusinessDayConvention>,
+
+    /// Rounding Strategy (half-even, half-up, half-down, truncate, ceiling,
+    /// floor), half-even by default
+    #[arg(long, value_parser = validate_rounding)]
+    rounding: Option<RoundingStrategy>,
+
+    /// Decimal places to round to, the currency's minor units by default
     #[arg(long)]
-    margin: Decimal,
+    rounding_precision: Option<u32>,
+
+    /// Where rounding applies (daily, period, total), daily by default
+    #[arg(long, value_parser = validate_rounding_stage)]
+    rounding_stage: Option<RoundingStage>,
+
+    /// Print the interest of each period before and after rounding
+    #[arg(long)]
+    rounding_report: bool,
+
+    /// CSV of daily exchange rates with date,pair,rate columns
+    #[arg(long)]
+    fx_rates: Option<PathBuf>,
+
+    /// Currency the schedule is also reported in, converted at each accrual
+    /// date's rate
+    #[arg(long, requires = "fx_rates", value_parser = validate_currency)]
+    reporting_currency: Option<CurrencyCode>,
+
+    /// Lowest all-in rate (base rate plus margin) accepted, as a percentage
+    #[arg(long, default_value = "-10", allow_negative_numbers = true)]
+    min_rate: Decimal,
+
+    /// Highest all-in rate (base rate plus margin) accepted, as a percentage
+    #[arg(long, default_value = "100", allow_negative_numbers = true)]
+    max_rate: Decimal,
+
+    /// Log the calculation steps to stderr, the same as --log-level debug
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Level of the calculation logs written to stderr (off, error, warn,
+    /// info, debug, trace), overrides RUST_LOG
+    #[arg(long, global = true, value_parser = validate_log_level)]
+    log_level: Option<LevelFilter>,
+
+    /// Output format: table, csv or jsonl for the daily schedule and total,
+    /// or json for the schedule, total, periods and rounding report
+    #[arg(long, default_value = "table", value_parser = validate_format)]
+    format: OutputFormat,
+
+    /// Write amounts and rates at full precision in the csv, json and jsonl
+    /// formats, rather than rounded as in the table
+    #[arg(long)]
+    unrounded: bool,
+
+    /// File to write the output to instead of stdout
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+/// Parses the flags, exiting with 6 on an unknown currency as for any other
+/// unsupported currency rather than as a usage error
+fn parse_args() -> Args {
+    Args::try_parse().unwrap_or_else(|e| {
+        let currency_arg = matches!(
+            e.get(ContextKind::InvalidArg),
+            Some(ContextValue::String(arg)) if arg.contains("-currency ")
+        );
+        if e.kind() == ErrorKind::ValueValidation && currency_arg {
+            let _ = e.print();
+            std::process::exit(6);
+        }
+        e.exit()
+    })
+}
+
+/// Logs to stderr at `level`, debug with `verbose`, or as RUST_LOG says
+fn init_logging(level: Option<LevelFilter>, verbose: bool) {
+    let mut logger = env_logger::Builder::from_default_env();
+    match (level, verbose) {
+        (Some(level), _) => {
+            logger.filter_level(level);
+        }
+        (None, true) => {
+            logger.filter_level(LevelFilter::Debug);
+        }
+        (None, false) => {}
+    }
+    logger.target(env_logger::Target::Stderr).init();
+}
+
+/// The loan flags, with the files they name read
+fn loan_overrides(args: &Args) -> LoanOverrides {
+    let base_rate = match (args.base_interest_rate, &args.base_rate_file) {
+        (Some(rate), _) => Some(RateSchedule::flat(rate)),
+        (None, Some(path)) => match RateSchedule::from_csv(path) {
+            Ok(base_rates) => Some(base_rates),
+            Err(e) => {
+                eprintln!("Error invalid base rate file: {}", e);
+                std::process::exit(1);
+            }
+        },
+        (None, None) => None,
+    };
+    let repayment = args.repayment.as_deref().map(|repayment| {
+        match RepaymentProfile::parse(repayment, args.balloon_residual.unwrap_or_default()) {
+            Ok(repayment) => repayment,
+            Err(e) => {
+                eprintln!("Error invalid repayment: {}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+    let events = args
+        .events
+        .as_deref()
+        .map(|path| match LoanEvent::from_csv(path) {
+            Ok(events) => events,
+            Err(e) => {
+                eprintln!("Error invalid events file: {}", e);
+                std::process::exit(1);
+            }
+        });
+    let calendar = (!args.calendars.is_empty()).then(|| {
+        let calendars = args
+            .calendars
+            .iter()
+            .map(|path| HolidayCalendar::from_file(path))
+            .collect::<Result<Vec<_>, _>>();
+        match calendars {
+            Ok(calendars) => HolidayCalendar::combine(calendars),
+            Err(e) => {
+                eprintln!("Error invalid calendar: {}", e);
+                std::process::exit(1);
+            }
+        }
+    });
+    let commitment_fee = match (args.commitment_fee, args.commitment_fee_margin_percentage) {
+        (_, Some(percentage)) => Some(CommitmentFee::MarginPercentage(percentage)),
+        (Some(rate), None) => Some(CommitmentFee::Rate(rate)),
+        (None, None) => None,
+    };
+
+    LoanOverrides {
+        start_date: args.start_date,
+        end_date: args.end_date,
+        loan_amount: args.loan_amount,
+        base_rate,
+        margin: args.margin,
+        currency: args.loan_currency,
+        day_count: args.day_count,
+        compounding: args.compounding,
+        rfr: args.rfr,
+        observation_method: args
+            .observation_shift
+            .then_some(ObservationMethod::ObservationShift),
+        lookback_days: args.lookback_days,
+        lockout_days: args.lockout_days,
+        payment_delay_days: args.payment_delay_days,
+        rfr_day_basis: args.rfr_day_basis,
+        floor: args.floor,
+        cap: args.cap,
+        floor_basis: args.floor_basis,
+        repayment,
+        balloon_residual: args.balloon_residual,
+        payment_frequency: args.payment_frequency,
+        stub: args.stub,
+        roll_day: args.roll_day,
+        end_of_month: args.end_of_month,
+        events,
+        facility_limit: args.facility_limit,
+        commitment_fee,
+        utilisation_tiers: (!args.utilisation_fees.is_empty())
+            .then(|| args.utilisation_fees.clone()),
+        calendar,
+        business_day_convention: args.business_day_convention,
+        rounding_strategy: args.rounding,
+        rounding_precision: args.rounding_precision,
+        rounding_stage: args.rounding_stage,
+    }
+}
+
+/// The loan file, or the loan of the required flags without one, with the
+/// other flags overriding its terms
+fn read_loan(args: &Args) -> Loan {
+    let overrides = loan_overrides(args);
+    let loan = match &args.loan_file {
+        Some(path) => match Loan::from_file(path) {
+            Ok(loan) => loan,
+            Err(e) => {
+                eprintln!("Error invalid loan file: {}", e);
+                std::process::exit(match e.unknown_currency() {
+                    Some(_) => 6,
+                    None => 1,
+                });
+            }
+        },
+        None => Loan::new(
+            args.start_date.expect("clap requires a start date"),
+            args.end_date.expect("clap requires an end date"),
+            args.loan_amount.expect("clap requires a loan amount"),
+            overrides
+                .base_rate
+                .clone()
+                .expect("clap requires a base rate"),
+            args.margin.expect("clap requires a margin"),
+            args.loan_currency.expect("clap requires a currency"),
+        ),
+    };
+    loan.with_overrides(&overrides).unwrap_or_else(|e| {
+        let message = match e {
+            OverrideError::RfrTermsWithoutRfr => {
+                "the RFR options need --rfr or RFR terms in the loan file"
+            }
+            OverrideError::FeesWithoutFacility => {
+                "the fee options need --facility-limit or a revolving facility in the loan file"
+            }
+        };
+        Args::command()
+            .error(ErrorKind::MissingRequiredArgument, message)
+            .exit()
+    })
+}
+
+/// Interest and fees of `schedule`, which has days to accrue once validated
+fn schedule_total(schedule: &Schedule) -> TotalInterest {
+    match schedule.calculate_interest() {
+        Ok(Some(total_interest)) => total_interest,
+        Ok(None) => {
+            eprintln!("Error invalid schedule: no days to accrue");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error invalid schedule: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `schedule` converted into `currency` at the rates of the FX file
+fn reporting(schedule: &Schedule, currency: CurrencyCode, fx_rates: &Path) -> Reporting {
+    let fx_rates = match FxRates::from_csv(fx_rates) {
+        Ok(fx_rates) => fx_rates,
+        Err(e) => {
+            eprintln!("Error invalid FX rates file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let converted = match schedule.convert(&fx_rates, currency) {
+        Ok(converted) => converted,
+        Err(e) => {
+            eprintln!("Error invalid FX rates file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    Reporting {
+        currency,
+        fx_rates,
+        total_interest: schedule_total(&converted),
+        schedule: converted,
+    }
+}
+
+/// Interest of each period and of the whole loan before and after rounding
+fn rounding_report(loan: &Loan, schedule: &Schedule) -> Report {
+    let differences = match schedule.rounding_differences() {
+        Ok(differences) => differences,
+        Err(e) => {
+            eprintln!("Error invalid schedule: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let rows = differences
+        .iter()
+        .map(|difference| {
+            let rounding_difference = match difference.difference() {
+                Ok(rounding_difference) => rounding_difference,
+                Err(e) => {
+                    eprintln!("Error invalid schedule: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            vec![
+                difference
+                    .period_end
+                    .map_or(Field::text("Total"), Field::text),
+                Field::Rate(difference.unrounded.value),
+                Field::Amount(difference.rounded),
+                Field::Rate(rounding_difference.value),
+                Field::text(loan.currency),
+            ]
+        })
+        .collect();
+    Report {
+        header: vec![
+            "Period End",
+            "Unrounded Interest",
+            "Rounded Interest",
+            "Rounding Difference",
+            "Currency",
+        ],
+        rows,
+        total: None,
+    }
 }
 
 fn main() {
-    let args: Args = Args::parse();
+    let args = parse_args();
+    init_logging(args.log_level, args.verbose);
+    if let Some(Command::Batch(batch)) = args.command {
+        run_batch(batch);
+        return;
+    }
 
-    let start_date = args.start_date;
-    let end_date = args.end_date;
-    let loan_amount = args.loan_amount;
-    let currency_arg = args.loan_currency;
-    let base_rate = args.base_interest_rate;
-    let margin = args.margin;
+    let loan = read_loan(&args);
+    let rate_limits = RateLimits {
+        min: args.min_rate,
+        max: args.max_rate,
+    };
+    let schedule = match loan
+        .validate(&rate_limits)
+        .and_then(|()| Schedule::new(&loan))
+    {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            eprintln!("Error invalid loan: {}", e);
+            std::process::exit(exit_code(&e));
+        }
+    };
+    let reporting = match (args.reporting_currency, &args.fx_rates) {
+        (Some(currency), Some(fx_rates)) => Some(reporting(&schedule, currency, fx_rates)),
+        _ => None,
+    };
 
-    let currency = match CurrencyCode::try_from(currency_arg.as_str()) {
-        Ok(currency) => currency,
+    let total_interest = schedule_total(&schedule);
+    let schedule_report = schedule_report(&loan, &schedule, &total_interest, reporting.as_ref());
+    let periods_report = match periods_report(&loan, &schedule) {
+        Ok(periods_report) => periods_report,
         Err(e) => {
-            eprintln!("Error invalid currency: {}", e);
+            eprintln!("Error invalid payment: {}", e);
             std::process::exit(1);
         }
     };
+    let rounding_report = args
+        .rounding_report
+        .then(|| rounding_report(&loan, &schedule));
 
-    let loan = Loan::new(
-        start_date,
-        end_date,
-        loan_amount,
-        base_rate,
-        margin,
-        currency,
-    );
+    let mut output = open_output(args.output.as_deref());
+    let written = write_reports(
+        &mut output,
+        args.format,
+        args.unrounded,
+        &schedule_report,
+        &periods_report,
+        rounding_report.as_ref(),
+    )
+    .and_then(|()| output.flush());
+    if let Err(e) = written {
+        eprintln!("Error writing output: {}", e);
+        std::process::exit(1);
+    }
+}
 
-    let schedule = Schedule::new(&loan);
+#[cfg(test)]
+mod tests {
+    use oneiro::rounding::RoundingPolicy;
 
-    // Create a table
-    let mut table = Table::new();
+    use super::*;
 
-    table.add_row(row![
-        "Accrual Date",
-        "Days Elapsed",
-        "Interest Without Margin",
-        "Interest With Margin",
-        "Currency",
-    ]);
-
-    schedule.entries.iter().for_each(|entry| {
-        table.add_row(row![
-            entry.accrual_date,
-            entry.days_elapsed,
-            entry.daily_interest_without_margin,
-            entry.daily_interest_with_margin,
-            loan.currency,
-        ]);
-    });
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // 1,000 at 5% plus 1% for three days of 2024, rounded at `stage`
+    fn three_day_loan(stage: RoundingStage) -> Loan {
+        Loan::new(
+            date(2024, 1, 1),
+            date(2024, 1, 3),
+            Decimal::from(1_000),
+            RateSchedule::flat(Decimal::from(5)),
+            Decimal::from(1),
+            CurrencyCode::GBP,
+        )
+        .with_rounding(RoundingPolicy {
+            stage,
+            ..RoundingPolicy::default()
+        })
+    }
+
+    // The loan's reports written in `format`
+    fn output(loan: &Loan, format: OutputFormat, unrounded: bool) -> String {
+        let schedule = Schedule::new(loan).unwrap();
+        let total_interest = schedule_total(&schedule);
+        let schedule_report = schedule_report(loan, &schedule, &total_interest, None);
+        let periods_report = periods_report(loan, &schedule).unwrap();
+        let rounding_report = rounding_report(loan, &schedule);
+        let mut out = Vec::new();
+        write_reports(
+            &mut out,
+            format,
+            unrounded,
+            &schedule_report,
+            &periods_report,
+            Some(&rounding_report),
+        )
+        .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn csv_has_the_table_columns_and_the_total_as_the_last_row() {
+        let csv = output(
+            &three_day_loan(RoundingStage::Daily),
+            OutputFormat::Csv,
+            false,
+        );
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines[0],
+            "accrual_date,days_elapsed,days_in_year,base_rate,opening_balance,\
+             interest_without_margin,interest_with_margin,accrued_interest,\
+             capitalised_interest,currency"
+        );
+        assert_eq!(
+            lines[1],
+            "2024-01-01,0,366,5,1000.00,0.14,0.16,0.16,0.00,GBP"
+        );
+        assert_eq!(lines[4], "Total,,,,,0.42,0.48,,,GBP");
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn unrounded_output_keeps_full_precision() {
+        let loan = three_day_loan(RoundingStage::Total);
+        let rounded = output(&loan, OutputFormat::Csv, false);
+        assert!(rounded
+            .lines()
+            .nth(1)
+            .unwrap()
+            .ends_with(",0.14,0.16,0.16,0.00,GBP"));
+        let unrounded = output(&loan, OutputFormat::Csv, true);
+        let first_day = unrounded
+            .lines()
+            .nth(1)
+            .unwrap()
+            .split(',')
+            .collect::<Vec<_>>();
+        // 1,000 * 6% / 366
+        assert_eq!(first_day[6], "0.1639344262295081967213115000");
+        assert_eq!(first_day[4], "1000");
+    }
+
+    #[test]
+    fn jsonl_has_one_object_per_day_then_the_total() {
+        let jsonl = output(
+            &three_day_loan(RoundingStage::Daily),
+            OutputFormat::Jsonl,
+            false,
+        );
+        let records = jsonl
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0]["accrual_date"], "2024-01-01");
+        assert_eq!(records[0]["days_elapsed"], 0);
+        assert_eq!(records[0]["interest_with_margin"], "0.16");
+        assert_eq!(records[3]["accrual_date"], "Total");
+        assert_eq!(records[3]["interest_with_margin"], "0.48");
+        assert_eq!(records[3]["days_elapsed"], Value::Null);
+    }
+
+    #[test]
+    fn json_has_the_schedule_total_periods_and_rounding_report() {
+        let json = output(
+            &three_day_loan(RoundingStage::Daily),
+            OutputFormat::Json,
+            false,
+        );
+        let report = serde_json::from_str::<Value>(&json).unwrap();
+        assert_eq!(
+            report
+                .as_object()
+                .unwrap()
+                .keys()
+                .map(String::as_str)
+                .collect::<Vec<_>>(),
+            ["schedule", "total", "periods", "rounding_differences"]
+        );
+        assert_eq!(report["schedule"].as_array().unwrap().len(), 3);
+        assert_eq!(report["schedule"][2]["accrual_date"], "2024-01-03");
+        assert_eq!(report["total"]["interest_with_margin"], "0.48");
+        assert_eq!(report["periods"].as_array().unwrap().len(), 1);
+    }
 
-    let total_interest = schedule.calculate_interest().unwrap();
+    #[test]
+    fn tables_show_every_report() {
+        let table = output(
+            &three_day_loan(RoundingStage::Daily),
+            OutputFormat::Table,
+            false,
+        );
+        assert!(table.contains("Accrual Date"));
+        assert!(table.contains("£0.48"));
+        assert!(table.contains("2024-01-03"));
+    }
 
-    table.add_row(row![
-        "Total",
-        "",
-        total_interest.without_margin,
-        total_interest.with_margin,
-        loan.currency
-    ]);
+    fn example_loan_args(flags: &[&str]) -> Args {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/loan.toml");
+        let loan_file = ["oneiro", "--loan-file", path.to_str().unwrap()];
+        Args::try_parse_from(loan_file.iter().chain(flags)).unwrap()
+    }
 
-    table.printstd();
+    #[test]
+    fn flags_override_the_loan_file() {
+        let loan = read_loan(&example_loan_args(&[
+            "--margin",
+            "3",
+            "--end-date",
+            "2025-06-30",
+            "--repayment",
+            "balloon",
+            "--balloon-residual",
+            "25",
+        ]));
+        assert_eq!(loan.margin, Decimal::from(3));
+        assert_eq!(loan.end_date, date(2025, 6, 30));
+        assert_eq!(
+            loan.repayment,
+            RepaymentProfile::Balloon {
+                residual_percentage: Decimal::from(25)
+            }
+        );
+        // Terms without a flag are the file's
+        assert_eq!(loan.start_date, date(2024, 1, 1));
+        assert_eq!(loan.day_count, DayCountConvention::Act365Fixed);
+        assert_eq!(loan.base_rate.fixings().len(), 2);
+        assert_eq!(loan.events.len(), 1);
+    }
+
+    #[test]
+    fn the_loan_file_is_read_as_it_is_without_flags() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/loan.toml");
+        let loan = read_loan(&example_loan_args(&[]));
+        assert_eq!(
+            format!("{:?}", loan),
+            format!("{:?}", Loan::from_file(&path).unwrap())
+        );
+    }
+
+    #[test]
+    fn rfr_and_fee_flags_need_terms_to_override() {
+        let override_error = |flags: &[&str]| {
+            let args = example_loan_args(flags);
+            let loan = Loan::from_file(args.loan_file.as_ref().unwrap()).unwrap();
+            loan.with_overrides(&loan_overrides(&args)).unwrap_err()
+        };
+        assert_eq!(
+            override_error(&["--lookback-days", "5"]),
+            OverrideError::RfrTermsWithoutRfr
+        );
+        assert_eq!(
+            override_error(&["--commitment-fee", "0.3"]),
+            OverrideError::FeesWithoutFacility
+        );
+    }
+
+    #[test]
+    fn formats_parse() {
+        assert_eq!(validate_format("CSV"), Ok(OutputFormat::Csv));
+        assert_eq!(validate_format("ndjson"), Ok(OutputFormat::Jsonl));
+        assert_eq!(
+            validate_format("xml"),
+            Err("Error unknown output format: xml".into())
+        );
+    }
 }
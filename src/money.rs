@@ -0,0 +1,286 @@
+use std::{
+    cmp::Ordering,
+    error::Error,
+    fmt::Display,
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+use rust_decimal::Decimal;
+
+use crate::currency::CurrencyCode;
+
+/// An amount in a currency
+///
+/// With the `serde` feature an amount is written as its decimal string and the
+/// currency as its ISO 4217 code, so no precision is lost:
+///
+/// ```
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use oneiro::{CurrencyCode, Money};
+/// use rust_decimal::Decimal;
+///
+/// let money = Money::new(Decimal::new(123_456, 2), CurrencyCode::GBP);
+/// let json = serde_json::to_string(&money).unwrap();
+/// assert_eq!(json, r#"{"amount":"1234.56","currency":"GBP"}"#);
+/// assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), money);
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "WireMoney", into = "WireMoney"))]
+pub struct Money {
+    /// Amount, kept at full precision until rounded
+    pub value: Decimal,
+    /// Currency of the amount
+    pub code: CurrencyCode,
+}
+
+/// Wire format of `Money`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WireMoney {
+    amount: Decimal,
+    currency: CurrencyCode,
+}
+
+#[cfg(feature = "serde")]
+impl From<WireMoney> for Money {
+    fn from(money: WireMoney) -> Self {
+        Money::new(money.amount, money.currency)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Money> for WireMoney {
+    fn from(money: Money) -> Self {
+        WireMoney {
+            amount: money.value,
+            currency: money.code,
+        }
+    }
+}
+
+/// Amounts in two different currencies were combined or compared
+#[derive(Debug)]
+pub struct CurrencyMismatchError {
+    left: CurrencyCode,
+    right: CurrencyCode,
+}
+
+impl CurrencyMismatchError {
+    fn new(left: CurrencyCode, right: CurrencyCode) -> Self {
+        Self { left, right }
+    }
+}
+
+impl Display for CurrencyMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error currency mismatch: {} and {}",
+            self.left, self.right
+        ))
+    }
+}
+
+impl Error for CurrencyMismatchError {}
+
+impl Money {
+    /// `value` in the currency `code`
+    pub fn new(value: Decimal, code: CurrencyCode) -> Self {
+        Self { value, code }
+    }
+
+    /// Nothing in the currency `code`
+    pub fn zero(code: CurrencyCode) -> Self {
+        Self::new(Decimal::ZERO, code)
+    }
+
+    /// The amount as it is displayed, rounded to the currency's minor units
+    /// with trailing zeros kept, or normalised when the currency has none
+    pub fn rounded(&self) -> Decimal {
+        let mut value = match self.code.minor_units() {
+            Some(minor_units) => {
+                let mut value = self.value.round_dp(minor_units);
+                value.rescale(minor_units);
+                value
+            }
+            None => self.value.normalize(),
+        };
+        // Rounding a small negative residual must not show as -0.00
+        if value.is_zero() {
+            value.set_sign_positive(true);
+        }
+        value
+    }
+
+    /// Whether the amount is zero
+    pub fn is_zero(&self) -> bool {
+        self.value.is_zero()
+    }
+
+    /// The amount without its sign
+    pub fn abs(&self) -> Self {
+        Self::new(self.value.abs(), self.code)
+    }
+
+    /// Adds up `amounts`, all of which must be in `code`. An empty iterator
+    /// sums to zero in `code`.
+    ///
+    /// `Money` does not implement [`std::iter::Sum`]: an empty sum has no
+    /// currency to be zero in, and `Sum` cannot fail on amounts in different
+    /// currencies, so the currency is passed here and a mismatch is returned
+    /// as an error.
+    pub fn sum(
+        code: CurrencyCode,
+        amounts: impl IntoIterator<Item = Money>,
+    ) -> Result<Money, CurrencyMismatchError> {
+        amounts
+            .into_iter()
+            .try_fold(Money::zero(code), |total, amount| total + amount)
+    }
+
+    fn same_currency(&self, other: &Money) -> Result<(), CurrencyMismatchError> {
+        if self.code == other.code {
+            Ok(())
+        } else {
+            Err(CurrencyMismatchError::new(self.code, other.code))
+        }
+    }
+}
+
+impl Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rounded = self.rounded();
+        let sign = if rounded.is_sign_negative() { "-" } else { "" };
+        let value = rounded.abs();
+        match self.code.symbol() {
+            Some(symbol) => f.write_fmt(format_args!("{}{}{}", sign, symbol, value)),
+            None => f.write_fmt(format_args!("{} {}{}", self.code, sign, value)),
+        }
+    }
+}
+
+impl Add for Money {
+    type Output = Result<Money, CurrencyMismatchError>;
+    fn add(self, rhs: Money) -> Self::Output {
+        self.same_currency(&rhs)?;
+        Ok(Money::new(self.value + rhs.value, self.code))
+    }
+}
+
+impl Sub for Money {
+    type Output = Result<Money, CurrencyMismatchError>;
+    fn sub(self, rhs: Money) -> Self::Output {
+        self.same_currency(&rhs)?;
+        Ok(Money::new(self.value - rhs.value, self.code))
+    }
+}
+
+impl Mul<Decimal> for Money {
+    type Output = Money;
+    fn mul(self, rhs: Decimal) -> Self::Output {
+        Money::new(self.value * rhs, self.code)
+    }
+}
+
+impl Div<Decimal> for Money {
+    type Output = Money;
+    fn div(self, rhs: Decimal) -> Self::Output {
+        Money::new(self.value / rhs, self.code)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+    fn neg(self) -> Self::Output {
+        Money::new(-self.value, self.code)
+    }
+}
+
+impl PartialEq for Money {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.value == other.value
+    }
+}
+
+/// Amounts in different currencies are unordered
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.same_currency(other).ok()?;
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gbp(value: i64) -> Money {
+        Money::new(Decimal::new(value, 2), CurrencyCode::GBP)
+    }
+
+    fn eur(value: i64) -> Money {
+        Money::new(Decimal::new(value, 2), CurrencyCode::EUR)
+    }
+
+    #[test]
+    fn amounts_in_one_currency_add_and_subtract() {
+        assert_eq!((gbp(1050) + gbp(225)).unwrap(), gbp(1275));
+        assert_eq!((gbp(1050) - gbp(1275)).unwrap(), gbp(-225));
+        assert_eq!(gbp(1000) * Decimal::new(15, 1), gbp(1500));
+        assert_eq!(gbp(1000) / Decimal::from(4), gbp(250));
+        assert_eq!(-gbp(1000), gbp(-1000));
+    }
+
+    #[test]
+    fn amounts_in_different_currencies_do_not_combine() {
+        assert_eq!(
+            (gbp(100) + eur(100)).unwrap_err().to_string(),
+            "Error currency mismatch: GBP and EUR"
+        );
+        assert_eq!(
+            (eur(100) - gbp(100)).unwrap_err().to_string(),
+            "Error currency mismatch: EUR and GBP"
+        );
+    }
+
+    #[test]
+    fn amounts_in_different_currencies_are_unequal_and_unordered() {
+        assert_ne!(gbp(100), eur(100));
+        assert_eq!(gbp(100).partial_cmp(&eur(100)), None);
+        assert!(gbp(100) < gbp(101));
+    }
+
+    #[test]
+    fn sums_are_in_the_given_currency() {
+        assert_eq!(
+            Money::sum(CurrencyCode::GBP, [gbp(100), gbp(250), gbp(-50)]).unwrap(),
+            gbp(300)
+        );
+        assert_eq!(
+            Money::sum(CurrencyCode::EUR, []).unwrap(),
+            Money::zero(CurrencyCode::EUR)
+        );
+        assert_eq!(
+            Money::sum(CurrencyCode::GBP, [gbp(100), eur(100)])
+                .unwrap_err()
+                .to_string(),
+            "Error currency mismatch: GBP and EUR"
+        );
+        assert!(Money::sum(CurrencyCode::GBP, [eur(100)]).is_err());
+    }
+
+    #[test]
+    fn amounts_display_rounded_with_their_symbol_or_code() {
+        assert_eq!(gbp(-123_456).to_string(), "-£1234.56");
+        assert_eq!(
+            Money::new(Decimal::new(-4, 3), CurrencyCode::GBP).to_string(),
+            "£0.00"
+        );
+        assert_eq!(
+            Money::new(Decimal::new(12_345, 1), CurrencyCode::CHF).to_string(),
+            "CHF 1234.50"
+        );
+    }
+}
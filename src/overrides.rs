@@ -0,0 +1,387 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+use crate::calendar::{BusinessDayConvention, HolidayCalendar};
+use crate::compounding::CompoundingMethod;
+use crate::currency::CurrencyCode;
+use crate::day_count::DayCountConvention;
+use crate::event::LoanEvent;
+use crate::facility::{CommitmentFee, FacilityType, RevolvingFacility, UtilisationTier};
+use crate::frequency::Frequency;
+use crate::loan::Loan;
+use crate::period::StubType;
+use crate::rate_bounds::FloorBasis;
+use crate::rate_schedule::RateSchedule;
+use crate::repayment::RepaymentProfile;
+use crate::rfr::{ObservationMethod, RfrTerms};
+use crate::rounding::{RoundingStage, RoundingStrategy};
+
+/// Terms that replace those of a loan, such as command line flags given
+/// alongside a loan file. Terms left as `None` or `false` keep the loan's.
+#[derive(Debug, Clone, Default)]
+pub struct LoanOverrides {
+    /// First day interest accrues
+    pub start_date: Option<NaiveDate>,
+    /// Last day interest accrues
+    pub end_date: Option<NaiveDate>,
+    /// Principal drawn at the start
+    pub loan_amount: Option<Decimal>,
+    /// Base rate fixings
+    pub base_rate: Option<RateSchedule>,
+    /// Margin over the base rate, as a percentage
+    pub margin: Option<Decimal>,
+    /// Currency of all amounts of the loan
+    pub currency: Option<CurrencyCode>,
+    /// How days accrue as a fraction of a year
+    pub day_count: Option<DayCountConvention>,
+    /// How accrued interest is added to the balance
+    pub compounding: Option<CompoundingMethod>,
+    /// Compounds the base rate as an overnight RFR, on the default
+    /// [`RfrTerms`] when the loan has none
+    pub rfr: bool,
+    /// How RFR fixings are observed
+    pub observation_method: Option<ObservationMethod>,
+    /// Business days the RFR observation is shifted back
+    pub lookback_days: Option<usize>,
+    /// Business days at the end of the interest period that reuse the RFR
+    /// fixing observed before them
+    pub lockout_days: Option<usize>,
+    /// Business days between the end of the interest period and payment
+    pub payment_delay_days: Option<usize>,
+    /// Days in the year the RFR fixings are quoted on
+    pub rfr_day_basis: Option<u32>,
+    /// Lowest rate, as a percentage
+    pub floor: Option<Decimal>,
+    /// Highest base rate, as a percentage
+    pub cap: Option<Decimal>,
+    /// What the floor is compared against
+    pub floor_basis: Option<FloorBasis>,
+    /// How principal is repaid
+    pub repayment: Option<RepaymentProfile>,
+    /// Residual of a balloon repayment, as a percentage of the loan amount
+    pub balloon_residual: Option<Decimal>,
+    /// How often interest and principal are paid
+    pub payment_frequency: Option<Frequency>,
+    /// Where the irregular period goes
+    pub stub: Option<StubType>,
+    /// Day of the month regular periods end on
+    pub roll_day: Option<u32>,
+    /// Regular periods end on month ends when rolling from a month end
+    pub end_of_month: bool,
+    /// Prepayments and drawdowns, in any order
+    pub events: Option<Vec<LoanEvent>>,
+    /// Committed limit, making a term loan a revolving credit facility
+    pub facility_limit: Option<Decimal>,
+    /// Fee charged on the undrawn part of a revolving facility
+    pub commitment_fee: Option<CommitmentFee>,
+    /// Fees charged on the drawn balance of a revolving facility
+    pub utilisation_tiers: Option<Vec<UtilisationTier>>,
+    /// Holidays used to adjust period ends and payment dates
+    pub calendar: Option<HolidayCalendar>,
+    /// How period ends and payment dates falling on holidays move
+    pub business_day_convention: Option<BusinessDayConvention>,
+    /// How interest and fees are rounded
+    pub rounding_strategy: Option<RoundingStrategy>,
+    /// Decimal places interest and fees are rounded to
+    pub rounding_precision: Option<u32>,
+    /// Where rounding applies
+    pub rounding_stage: Option<RoundingStage>,
+}
+
+/// Overrides that do not apply to the loan they are given for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverrideError {
+    /// RFR observation terms for a loan that does not compound an RFR
+    RfrTermsWithoutRfr,
+    /// Commitment or utilisation fees for a term loan
+    FeesWithoutFacility,
+}
+
+impl Display for OverrideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideError::RfrTermsWithoutRfr => {
+                f.write_str("Error RFR terms given for a loan without RFR compounding")
+            }
+            OverrideError::FeesWithoutFacility => {
+                f.write_str("Error facility fees given for a term loan")
+            }
+        }
+    }
+}
+
+impl Error for OverrideError {}
+
+impl LoanOverrides {
+    // Whether any of the RFR observation terms is given
+    fn has_rfr_terms(&self) -> bool {
+        self.observation_method.is_some()
+            || self.lookback_days.is_some()
+            || self.lockout_days.is_some()
+            || self.payment_delay_days.is_some()
+            || self.rfr_day_basis.is_some()
+    }
+
+    // Whether any of the revolving facility fees is given
+    fn has_fees(&self) -> bool {
+        self.commitment_fee.is_some() || self.utilisation_tiers.is_some()
+    }
+
+    pub(crate) fn apply(&self, mut loan: Loan) -> Result<Loan, OverrideError> {
+        if let Some(start_date) = self.start_date {
+            loan.start_date = start_date;
+        }
+        if let Some(end_date) = self.end_date {
+            loan.end_date = end_date;
+        }
+        if let Some(loan_amount) = self.loan_amount {
+            loan.loan_amount = loan_amount;
+        }
+        if let Some(base_rate) = &self.base_rate {
+            loan.base_rate = base_rate.clone();
+        }
+        if let Some(margin) = self.margin {
+            loan.margin = margin;
+        }
+        if let Some(currency) = self.currency {
+            loan.currency = currency;
+        }
+        if let Some(day_count) = self.day_count {
+            loan.day_count = day_count;
+        }
+        if let Some(compounding) = self.compounding {
+            loan.compounding = compounding;
+        }
+
+        if self.rfr && loan.rfr.is_none() {
+            loan.rfr = Some(RfrTerms::default());
+        }
+        match &mut loan.rfr {
+            Some(rfr) => {
+                if let Some(method) = self.observation_method {
+                    rfr.method = method;
+                }
+                if let Some(lookback_days) = self.lookback_days {
+                    rfr.lookback_days = lookback_days;
+                }
+                if let Some(lockout_days) = self.lockout_days {
+                    rfr.lockout_days = lockout_days;
+                }
+                if let Some(payment_delay_days) = self.payment_delay_days {
+                    rfr.payment_delay_days = payment_delay_days;
+                }
+                if let Some(day_basis) = self.rfr_day_basis {
+                    rfr.day_basis = day_basis;
+                }
+            }
+            None if self.has_rfr_terms() => return Err(OverrideError::RfrTermsWithoutRfr),
+            None => {}
+        }
+
+        if let Some(floor) = self.floor {
+            loan.rate_bounds.floor = Some(floor);
+        }
+        if let Some(cap) = self.cap {
+            loan.rate_bounds.cap = Some(cap);
+        }
+        if let Some(floor_basis) = self.floor_basis {
+            loan.rate_bounds.floor_basis = floor_basis;
+        }
+
+        if let Some(repayment) = self.repayment {
+            loan.repayment = repayment;
+        }
+        if let (
+            RepaymentProfile::Balloon {
+                residual_percentage,
+            },
+            Some(balloon_residual),
+        ) = (&mut loan.repayment, self.balloon_residual)
+        {
+            *residual_percentage = balloon_residual;
+        }
+
+        if let Some(payment_frequency) = self.payment_frequency {
+            loan.payment_frequency = Some(payment_frequency);
+        }
+        if let Some(stub) = self.stub {
+            loan.period_rules.stub = stub;
+        }
+        if let Some(roll_day) = self.roll_day {
+            loan.period_rules.roll_day = Some(roll_day);
+        }
+        if self.end_of_month {
+            loan.period_rules.end_of_month = true;
+        }
+        if let Some(events) = &self.events {
+            loan = loan.with_events(events.clone());
+        }
+
+        if let Some(limit) = self.facility_limit {
+            match &mut loan.facility {
+                FacilityType::Revolving(facility) => facility.limit = limit,
+                FacilityType::Term => {
+                    loan.facility = FacilityType::Revolving(RevolvingFacility::new(
+                        limit,
+                        CommitmentFee::Rate(Decimal::ZERO),
+                        Vec::new(),
+                    ))
+                }
+            }
+        }
+        match &mut loan.facility {
+            FacilityType::Revolving(facility) => {
+                if let Some(commitment_fee) = self.commitment_fee {
+                    facility.commitment_fee = commitment_fee;
+                }
+                if let Some(utilisation_tiers) = &self.utilisation_tiers {
+                    *facility = RevolvingFacility::new(
+                        facility.limit,
+                        facility.commitment_fee,
+                        utilisation_tiers.clone(),
+                    );
+                }
+            }
+            FacilityType::Term if self.has_fees() => {
+                return Err(OverrideError::FeesWithoutFacility)
+            }
+            FacilityType::Term => {}
+        }
+
+        if let Some(calendar) = &self.calendar {
+            loan.calendar = calendar.clone();
+        }
+        if let Some(business_day_convention) = self.business_day_convention {
+            loan.business_day_convention = business_day_convention;
+        }
+
+        if let Some(strategy) = self.rounding_strategy {
+            loan.rounding.strategy = strategy;
+        }
+        if let Some(precision) = self.rounding_precision {
+            loan.rounding.precision = Some(precision);
+        }
+        if let Some(stage) = self.rounding_stage {
+            loan.rounding.stage = stage;
+        }
+        Ok(loan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn one_year_loan() -> Loan {
+        Loan::new(
+            date(2024, 1, 1),
+            date(2024, 12, 31),
+            Decimal::from(1_000_000),
+            RateSchedule::flat(Decimal::new(35, 1)),
+            Decimal::from(2),
+            CurrencyCode::GBP,
+        )
+    }
+
+    #[test]
+    fn given_terms_replace_the_loans_and_the_rest_are_kept() {
+        let overrides = LoanOverrides {
+            end_date: Some(date(2025, 6, 30)),
+            margin: Some(Decimal::from(3)),
+            cap: Some(Decimal::from(5)),
+            ..LoanOverrides::default()
+        };
+        let loan = one_year_loan().with_overrides(&overrides).unwrap();
+        assert_eq!(loan.start_date, date(2024, 1, 1));
+        assert_eq!(loan.end_date, date(2025, 6, 30));
+        assert_eq!(loan.loan_amount, Decimal::from(1_000_000));
+        assert_eq!(loan.margin, Decimal::from(3));
+        assert_eq!(loan.rate_bounds.cap, Some(Decimal::from(5)));
+        assert_eq!(loan.rate_bounds.floor, None);
+    }
+
+    #[test]
+    fn no_overrides_leave_the_loan_as_it_was() {
+        let loan = one_year_loan()
+            .with_overrides(&LoanOverrides::default())
+            .unwrap();
+        assert_eq!(format!("{:?}", loan), format!("{:?}", one_year_loan()));
+    }
+
+    #[test]
+    fn rfr_terms_need_an_rfr_loan() {
+        let lookback = LoanOverrides {
+            lookback_days: Some(5),
+            ..LoanOverrides::default()
+        };
+        assert_eq!(
+            one_year_loan().with_overrides(&lookback).unwrap_err(),
+            OverrideError::RfrTermsWithoutRfr
+        );
+
+        let rfr = LoanOverrides {
+            rfr: true,
+            ..lookback
+        };
+        let terms = one_year_loan().with_overrides(&rfr).unwrap().rfr.unwrap();
+        assert_eq!(terms.lookback_days, 5);
+        assert_eq!(terms.day_basis, RfrTerms::default().day_basis);
+    }
+
+    #[test]
+    fn fees_need_a_revolving_facility() {
+        let fee = LoanOverrides {
+            commitment_fee: Some(CommitmentFee::Rate(Decimal::new(3, 1))),
+            ..LoanOverrides::default()
+        };
+        assert_eq!(
+            one_year_loan().with_overrides(&fee).unwrap_err(),
+            OverrideError::FeesWithoutFacility
+        );
+
+        let facility = LoanOverrides {
+            facility_limit: Some(Decimal::from(2_000_000)),
+            ..fee
+        };
+        match one_year_loan().with_overrides(&facility).unwrap().facility {
+            FacilityType::Revolving(facility) => {
+                assert_eq!(facility.limit, Decimal::from(2_000_000));
+                assert_eq!(
+                    facility.commitment_fee,
+                    CommitmentFee::Rate(Decimal::new(3, 1))
+                );
+            }
+            FacilityType::Term => panic!("expected a revolving facility"),
+        }
+    }
+
+    #[test]
+    fn balloon_residuals_only_apply_to_balloon_loans() {
+        let residual = LoanOverrides {
+            balloon_residual: Some(Decimal::from(40)),
+            ..LoanOverrides::default()
+        };
+        let loan = one_year_loan().with_overrides(&residual).unwrap();
+        assert_eq!(loan.repayment, RepaymentProfile::Bullet);
+
+        let balloon = LoanOverrides {
+            repayment: Some(RepaymentProfile::Balloon {
+                residual_percentage: Decimal::ZERO,
+            }),
+            ..residual
+        };
+        let loan = one_year_loan().with_overrides(&balloon).unwrap();
+        assert_eq!(
+            loan.repayment,
+            RepaymentProfile::Balloon {
+                residual_percentage: Decimal::from(40)
+            }
+        );
+    }
+}
@@ -0,0 +1,397 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::day_count::is_last_day_of_month;
+use crate::frequency::Frequency;
+use crate::loan::{Entry, Payment};
+use crate::money::Money;
+
+/// Where an irregular period goes when the term is not a whole number of
+/// regular periods
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum StubType {
+    /// A short first period, regular periods roll back from the end date
+    ShortFront,
+    /// A first period longer than regular, regular periods roll back from
+    /// the end date
+    LongFront,
+    /// A short last period, regular periods roll forward from the start date
+    #[default]
+    ShortBack,
+    /// A last period longer than regular, regular periods roll forward from
+    /// the start date
+    LongBack,
+}
+
+impl Display for StubType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StubType::ShortFront => f.write_str("short-front"),
+            StubType::LongFront => f.write_str("long-front"),
+            StubType::ShortBack => f.write_str("short-back"),
+            StubType::LongBack => f.write_str("long-back"),
+        }
+    }
+}
+
+/// Stub type that is not recognised
+#[derive(Debug)]
+pub struct UnknownStubTypeError {
+    stub: String,
+}
+
+impl UnknownStubTypeError {
+    fn new(stub: String) -> Self {
+        Self { stub }
+    }
+}
+
+impl Display for UnknownStubTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Error unknown stub type: {}", self.stub))
+    }
+}
+
+impl Error for UnknownStubTypeError {}
+
+impl TryFrom<&str> for StubType {
+    type Error = UnknownStubTypeError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().replace(['_', ' '], "-").as_str() {
+            "short-front" => Ok(StubType::ShortFront),
+            "long-front" => Ok(StubType::LongFront),
+            "short-back" => Ok(StubType::ShortBack),
+            "long-back" => Ok(StubType::LongBack),
+            _ => Err(UnknownStubTypeError::new(value.into())),
+        }
+    }
+}
+
+/// How regular interest periods are laid out over the term of a loan
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct PeriodRules {
+    /// Where the irregular period goes
+    pub stub: StubType,
+    /// Day of the month regular periods end on, defaults to the day of the
+    /// date they roll from
+    pub roll_day: Option<u32>,
+    /// Regular periods end on the last day of the month when the date they
+    /// roll from is a month end
+    pub end_of_month: bool,
+}
+
+fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32
+}
+
+impl PeriodRules {
+    fn rolls_back(&self) -> bool {
+        matches!(self.stub, StubType::ShortFront | StubType::LongFront)
+    }
+
+    // The date regular periods roll from
+    fn anchor(&self, start: NaiveDate, end: NaiveDate) -> NaiveDate {
+        if self.rolls_back() {
+            end
+        } else {
+            start
+        }
+    }
+
+    // Regular date `months` months from the anchor, honouring the roll day and
+    // end of month rule
+    fn roll(&self, anchor: NaiveDate, months: i32) -> NaiveDate {
+        let first_of_month = anchor.with_day(1).expect("first of month");
+        let month = if months >= 0 {
+            first_of_month + Months::new(months as u32)
+        } else {
+            first_of_month - Months::new(months.unsigned_abs())
+        };
+        let last_day = (month + Months::new(1))
+            .pred_opt()
+            .expect("date out of range")
+            .day();
+        let day = if self.end_of_month && is_last_day_of_month(anchor) {
+            last_day
+        } else {
+            self.roll_day.unwrap_or(anchor.day()).min(last_day)
+        };
+        month.with_day(day).expect("day within month")
+    }
+
+    /// Unadjusted ends of the interest periods between `start` and `end`, the
+    /// last one always being `end`. Roll days past the end of a month end on
+    /// its last day. Panics on a roll day of 0, which [`Loan::validate`]
+    /// rejects.
+    ///
+    /// [`Loan::validate`]: crate::Loan::validate
+    pub fn period_ends(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        frequency: Frequency,
+    ) -> Vec<NaiveDate> {
+        let step = frequency.months() as i32;
+        let mut dates = Vec::new();
+        if self.rolls_back() {
+            let mut periods = 1;
+            loop {
+                let date = self.roll(end, -step * periods);
+                if date <= start {
+                    break;
+                }
+                dates.push(date);
+                periods += 1;
+            }
+            dates.reverse();
+            // A long front stub absorbs the first regular period
+            if self.stub == StubType::LongFront
+                && self.roll(end, -step * periods) < start
+                && !dates.is_empty()
+            {
+                dates.remove(0);
+            }
+        } else {
+            let mut periods = 1;
+            loop {
+                let date = self.roll(start, step * periods);
+                if date >= end {
+                    // A long back stub absorbs the last regular period
+                    if self.stub == StubType::LongBack && date > end {
+                        dates.pop();
+                    }
+                    break;
+                }
+                dates.push(date);
+                periods += 1;
+            }
+        }
+        dates.push(end);
+        dates
+    }
+
+    /// Regular period around `date` on the grid the periods roll on, stubs
+    /// are measured against the notional regular period they fall in
+    pub fn reference_period(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        frequency: Frequency,
+        date: NaiveDate,
+    ) -> (NaiveDate, NaiveDate) {
+        let step = frequency.months() as i32;
+        let anchor = self.anchor(start, end);
+        let mut periods = months_between(anchor, date).div_euclid(step);
+        while self.roll(anchor, step * periods) > date {
+            periods -= 1;
+        }
+        while self.roll(anchor, step * (periods + 1)) <= date {
+            periods += 1;
+        }
+        (
+            self.roll(anchor, step * periods),
+            self.roll(anchor, step * (periods + 1)),
+        )
+    }
+}
+
+/// An interest period of a schedule, grouping its daily entries with the
+/// payment made at its end
+#[derive(Debug, Clone, Copy)]
+pub struct Period<'a> {
+    /// Daily entries of the period
+    pub entries: &'a [Entry],
+    /// Payment made at the end of the period
+    pub payment: &'a Payment,
+}
+
+impl Period<'_> {
+    /// First day accruing in the period
+    pub fn start_date(&self) -> NaiveDate {
+        self.entries[0].accrual_date
+    }
+
+    /// Last day accruing in the period
+    pub fn end_date(&self) -> NaiveDate {
+        self.payment.period_end
+    }
+
+    /// Number of days accruing in the period
+    pub fn days(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Interest due on the payment date
+    pub fn interest_due(&self) -> Money {
+        self.payment.interest
+    }
+
+    /// Business day adjusted date the period's interest is paid on
+    pub fn payment_date(&self) -> NaiveDate {
+        self.payment.payment_date
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn rules(stub: StubType) -> PeriodRules {
+        PeriodRules {
+            stub,
+            ..PeriodRules::default()
+        }
+    }
+
+    #[test]
+    fn roll_days_past_the_end_of_a_month_end_on_its_last_day() {
+        let rules = PeriodRules {
+            roll_day: Some(31),
+            ..PeriodRules::default()
+        };
+        assert_eq!(
+            rules.period_ends(date(2024, 1, 15), date(2024, 6, 30), Frequency::Monthly),
+            vec![
+                date(2024, 2, 29),
+                date(2024, 3, 31),
+                date(2024, 4, 30),
+                date(2024, 5, 31),
+                date(2024, 6, 30),
+            ]
+        );
+    }
+
+    #[test]
+    fn roll_days_return_after_a_short_month() {
+        let rules = PeriodRules {
+            roll_day: Some(30),
+            ..PeriodRules::default()
+        };
+        assert_eq!(
+            rules.period_ends(date(2023, 1, 30), date(2023, 4, 30), Frequency::Monthly),
+            vec![date(2023, 2, 28), date(2023, 3, 30), date(2023, 4, 30)]
+        );
+    }
+
+    #[test]
+    fn back_stubs_roll_forward_from_the_start_date() {
+        let (start, end) = (date(2024, 1, 15), date(2024, 12, 1));
+        assert_eq!(
+            rules(StubType::ShortBack).period_ends(start, end, Frequency::Quarterly),
+            vec![
+                date(2024, 4, 15),
+                date(2024, 7, 15),
+                date(2024, 10, 15),
+                date(2024, 12, 1),
+            ]
+        );
+        assert_eq!(
+            rules(StubType::LongBack).period_ends(start, end, Frequency::Quarterly),
+            vec![date(2024, 4, 15), date(2024, 7, 15), date(2024, 12, 1)]
+        );
+    }
+
+    #[test]
+    fn front_stubs_roll_back_from_the_end_date() {
+        let (start, end) = (date(2024, 1, 15), date(2024, 12, 1));
+        assert_eq!(
+            rules(StubType::ShortFront).period_ends(start, end, Frequency::Quarterly),
+            vec![
+                date(2024, 3, 1),
+                date(2024, 6, 1),
+                date(2024, 9, 1),
+                date(2024, 12, 1),
+            ]
+        );
+        assert_eq!(
+            rules(StubType::LongFront).period_ends(start, end, Frequency::Quarterly),
+            vec![date(2024, 6, 1), date(2024, 9, 1), date(2024, 12, 1)]
+        );
+    }
+
+    #[test]
+    fn terms_of_whole_periods_have_no_stub() {
+        for stub in [
+            StubType::ShortFront,
+            StubType::LongFront,
+            StubType::ShortBack,
+            StubType::LongBack,
+        ] {
+            assert_eq!(
+                rules(stub).period_ends(date(2024, 1, 15), date(2024, 7, 15), Frequency::Quarterly),
+                vec![date(2024, 4, 15), date(2024, 7, 15)],
+                "{}",
+                stub
+            );
+        }
+    }
+
+    #[test]
+    fn the_end_of_month_rule_keeps_periods_on_month_ends() {
+        let (start, end) = (date(2024, 2, 29), date(2024, 5, 31));
+        let end_of_month = PeriodRules {
+            end_of_month: true,
+            ..PeriodRules::default()
+        };
+        assert_eq!(
+            end_of_month.period_ends(start, end, Frequency::Monthly),
+            vec![date(2024, 3, 31), date(2024, 4, 30), date(2024, 5, 31)]
+        );
+        assert_eq!(
+            PeriodRules::default().period_ends(start, end, Frequency::Monthly),
+            vec![
+                date(2024, 3, 29),
+                date(2024, 4, 29),
+                date(2024, 5, 29),
+                date(2024, 5, 31),
+            ]
+        );
+    }
+
+    #[test]
+    fn stubs_are_measured_against_the_regular_period_they_fall_in() {
+        let (start, end) = (date(2024, 1, 15), date(2024, 12, 1));
+        assert_eq!(
+            rules(StubType::ShortFront).reference_period(
+                start,
+                end,
+                Frequency::Quarterly,
+                date(2024, 2, 1)
+            ),
+            (date(2023, 12, 1), date(2024, 3, 1))
+        );
+        assert_eq!(
+            rules(StubType::ShortBack).reference_period(
+                start,
+                end,
+                Frequency::Quarterly,
+                date(2024, 11, 1)
+            ),
+            (date(2024, 10, 15), date(2025, 1, 15))
+        );
+    }
+
+    #[test]
+    fn stub_types_are_read_in_any_case_and_separator() {
+        assert_eq!(
+            StubType::try_from("Long_Front").unwrap(),
+            StubType::LongFront
+        );
+        assert_eq!(
+            StubType::try_from("short back").unwrap(),
+            StubType::ShortBack
+        );
+        assert!(StubType::try_from("middle").is_err());
+    }
+}
@@ -0,0 +1,578 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    str::FromStr,
+};
+
+use chrono::NaiveDate;
+use csv::StringRecord;
+use log::{debug, warn};
+use rayon::prelude::*;
+use rust_decimal::Decimal;
+use serde_json::Value;
+
+use crate::calendar::BusinessDayConvention;
+use crate::compounding::CompoundingMethod;
+use crate::currency::CurrencyCode;
+use crate::day_count::DayCountConvention;
+use crate::frequency::Frequency;
+use crate::loan::{Loan, RateLimits, Schedule, TotalInterest};
+use crate::rate_schedule::RateSchedule;
+use crate::repayment::RepaymentProfile;
+
+/// Columns of a CSV portfolio. The loan terms `Loan::new` takes are required,
+/// the others default as they do there and `id` to the line number.
+pub const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "start_date",
+    "end_date",
+    "loan_amount",
+    "currency",
+    "base_rate",
+    "margin",
+    "day_count",
+    "compounding",
+    "repayment",
+    "balloon_residual",
+    "payment_frequency",
+    "floor",
+    "cap",
+    "business_day_convention",
+];
+
+/// Error reading or calculating a loan of a portfolio, with the line of the
+/// portfolio file it occurred on
+#[derive(Debug, Clone)]
+pub struct PortfolioError {
+    line: u64,
+    message: String,
+}
+
+impl PortfolioError {
+    fn new(line: u64, message: String) -> Self {
+        Self { line, message }
+    }
+
+    /// Line of the file the error occurred on, 0 when it is not tied to a line
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl Display for PortfolioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error in portfolio on line {}: {}",
+            self.line, self.message
+        ))
+    }
+}
+
+impl Error for PortfolioError {}
+
+/// A loan read from one row of a portfolio file
+#[derive(Debug)]
+pub struct PortfolioRow {
+    /// Line of the file the loan was read from
+    pub line: u64,
+    /// Identifier of the loan in reports, the line number when the row has
+    /// none
+    pub id: String,
+    /// The loan, or why the row could not be read
+    pub loan: Result<Loan, PortfolioError>,
+}
+
+/// Total interest of one loan of a portfolio
+#[derive(Debug)]
+pub struct LoanSummary {
+    /// Line of the file the loan was read from
+    pub line: u64,
+    /// Identifier of the loan
+    pub id: String,
+    /// Interest and fees over the term, or why the loan could not be
+    /// calculated
+    pub total_interest: Result<TotalInterest, PortfolioError>,
+}
+
+/// Loans calculated together, one per row of a portfolio file
+#[derive(Debug, Default)]
+pub struct Portfolio {
+    /// Rows in the order they were read
+    pub rows: Vec<PortfolioRow>,
+    /// Threads the loans are calculated on, one per CPU when not given
+    pub threads: Option<usize>,
+}
+
+// A record of a CSV portfolio with its header
+struct CsvRow<'a> {
+    headers: &'a StringRecord,
+    record: &'a StringRecord,
+}
+
+impl CsvRow<'_> {
+    fn get(&self, column: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .position(|header| header.trim() == column)
+            .and_then(|index| self.record.get(index))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    fn required(&self, column: &str) -> Result<&str, String> {
+        self.get(column)
+            .ok_or_else(|| format!("missing {}", column))
+    }
+
+    fn date(&self, column: &str) -> Result<NaiveDate, String> {
+        let value = self.required(column)?;
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|e| format!("invalid {} {:?}: {}", column, value, e))
+    }
+
+    fn decimal(&self, column: &str) -> Result<Option<Decimal>, String> {
+        self.get(column)
+            .map(|value| {
+                Decimal::from_str(value)
+                    .map_err(|e| format!("invalid {} {:?}: {}", column, value, e))
+            })
+            .transpose()
+    }
+
+    fn required_decimal(&self, column: &str) -> Result<Decimal, String> {
+        self.decimal(column)?
+            .ok_or_else(|| format!("missing {}", column))
+    }
+
+    fn loan(&self) -> Result<Loan, String> {
+        let currency =
+            CurrencyCode::try_from(self.required("currency")?).map_err(|e| e.to_string())?;
+        let mut loan = Loan::new(
+            self.date("start_date")?,
+            self.date("end_date")?,
+            self.required_decimal("loan_amount")?,
+            RateSchedule::flat(self.required_decimal("base_rate")?),
+            self.required_decimal("margin")?,
+            currency,
+        );
+        if let Some(day_count) = self.get("day_count") {
+            loan.day_count = DayCountConvention::try_from(day_count).map_err(|e| e.to_string())?;
+        }
+        if let Some(compounding) = self.get("compounding") {
+            loan.compounding =
+                CompoundingMethod::try_from(compounding).map_err(|e| e.to_string())?;
+        }
+        if let Some(repayment) = self.get("repayment") {
+            let balloon_residual = self.decimal("balloon_residual")?.unwrap_or_default();
+            loan.repayment =
+                RepaymentProfile::parse(repayment, balloon_residual).map_err(|e| e.to_string())?;
+        }
+        if let Some(payment_frequency) = self.get("payment_frequency") {
+            loan.payment_frequency =
+                Some(Frequency::try_from(payment_frequency).map_err(|e| e.to_string())?);
+        }
+        loan.rate_bounds.floor = self.decimal("floor")?;
+        loan.rate_bounds.cap = self.decimal("cap")?;
+        if let Some(convention) = self.get("business_day_convention") {
+            loan.business_day_convention =
+                BusinessDayConvention::try_from(convention).map_err(|e| e.to_string())?;
+        }
+        Ok(loan)
+    }
+}
+
+impl Portfolio {
+    /// A portfolio of `rows` calculated on one thread per CPU
+    pub fn new(rows: Vec<PortfolioRow>) -> Self {
+        Self {
+            rows,
+            threads: None,
+        }
+    }
+
+    /// Number of threads the loans are calculated on
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Reads a portfolio from a `.csv` file with the [`CSV_COLUMNS`], or a
+    /// `.jsonl` file with one serialized loan per line and an optional `id`.
+    /// A row that cannot be read keeps its error so the rest of the
+    /// portfolio can still be calculated, only an unreadable file fails as a
+    /// whole.
+    pub fn from_file(path: &Path) -> Result<Self, PortfolioError> {
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        match extension.as_deref() {
+            Some("csv") => Self::from_csv(path),
+            Some("jsonl" | "ndjson") => Self::from_jsonl(path),
+            _ => Err(PortfolioError::new(
+                0,
+                format!(
+                    "unknown format of {}, expected a .csv or .jsonl file",
+                    path.display()
+                ),
+            )),
+        }
+    }
+
+    fn from_csv(path: &Path) -> Result<Self, PortfolioError> {
+        let mut reader =
+            csv::Reader::from_path(path).map_err(|e| PortfolioError::new(0, e.to_string()))?;
+        let headers = reader
+            .headers()
+            .map_err(|e| PortfolioError::new(1, e.to_string()))?
+            .clone();
+        if let Some(column) = headers
+            .iter()
+            .find(|column| !CSV_COLUMNS.contains(&column.trim()))
+        {
+            return Err(PortfolioError::new(
+                1,
+                format!("unknown column {:?}", column),
+            ));
+        }
+
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            let row = match record {
+                Ok(record) => {
+                    let line = record.position().map_or(0, |position| position.line());
+                    let row = CsvRow {
+                        headers: &headers,
+                        record: &record,
+                    };
+                    PortfolioRow {
+                        line,
+                        id: row.get("id").map_or_else(|| line.to_string(), String::from),
+                        loan: row
+                            .loan()
+                            .map_err(|message| PortfolioError::new(line, message)),
+                    }
+                }
+                Err(e) => {
+                    let line = e.position().map_or(0, |position| position.line());
+                    PortfolioRow {
+                        line,
+                        id: line.to_string(),
+                        loan: Err(PortfolioError::new(line, e.to_string())),
+                    }
+                }
+            };
+            rows.push(row);
+        }
+        Ok(Self::new(rows))
+    }
+
+    fn from_jsonl(path: &Path) -> Result<Self, PortfolioError> {
+        let file = File::open(path).map_err(|e| PortfolioError::new(0, e.to_string()))?;
+        let mut rows = Vec::new();
+        for (index, text) in BufReader::new(file).lines().enumerate() {
+            let line = index as u64 + 1;
+            let text = text.map_err(|e| PortfolioError::new(line, e.to_string()))?;
+            if text.trim().is_empty() {
+                continue;
+            }
+            let mut id = line.to_string();
+            let loan = serde_json::from_str::<Value>(&text)
+                .and_then(|mut value| {
+                    match value.as_object_mut().and_then(|loan| loan.remove("id")) {
+                        Some(Value::String(text)) => id = text,
+                        Some(Value::Null) | None => {}
+                        Some(value) => id = value.to_string(),
+                    }
+                    serde_json::from_value(value)
+                })
+                .map_err(|e| PortfolioError::new(line, e.to_string()));
+            rows.push(PortfolioRow { line, id, loan });
+        }
+        Ok(Self::new(rows))
+    }
+
+    /// Validates and calculates every loan across the portfolio's threads,
+    /// returning their totals in row order whatever order they finish in.
+    /// `on_schedule` is called with each loan's schedule before it is
+    /// dropped, e.g. to write it out, and an error it returns fails that loan
+    /// alone. It is called from several threads at once.
+    pub fn calculate<F>(&self, limits: &RateLimits, on_schedule: F) -> Vec<LoanSummary>
+    where
+        F: Fn(&PortfolioRow, &Schedule) -> Result<(), Box<dyn Error + Send + Sync>> + Sync,
+    {
+        self.summarise(|row| {
+            let error = |message: String| PortfolioError::new(row.line, message);
+            let loan = row.loan.as_ref().map_err(Clone::clone)?;
+            loan.validate(limits).map_err(|e| error(e.to_string()))?;
+            debug!("calculating loan {} from line {}", row.id, row.line);
+            let schedule = Schedule::new(loan).map_err(|e| error(e.to_string()))?;
+            let total_interest = schedule
+                .calculate_interest()
+                .map_err(|e| error(e.to_string()))?
+                .ok_or_else(|| error("no days to accrue".into()))?;
+            on_schedule(row, &schedule).map_err(|e| error(e.to_string()))?;
+            Ok(total_interest)
+        })
+    }
+
+    /// Validates every loan and calculates its totals with
+    /// `Loan::total_interest`, the same totals as `calculate` without
+    /// building the schedules
+    pub fn total_interest(&self, limits: &RateLimits) -> Vec<LoanSummary> {
+        self.summarise(|row| {
+            let error = |message: String| PortfolioError::new(row.line, message);
+            let loan = row.loan.as_ref().map_err(Clone::clone)?;
+            loan.validate(limits).map_err(|e| error(e.to_string()))?;
+            debug!(
+                "calculating totals of loan {} from line {}",
+                row.id, row.line
+            );
+            loan.total_interest()
+                .map_err(|e| error(e.to_string()))?
+                .ok_or_else(|| error("no days to accrue".into()))
+        })
+    }
+
+    // Summarises the rows with `calculate` on the portfolio's threads
+    fn summarise<C>(&self, calculate: C) -> Vec<LoanSummary>
+    where
+        C: Fn(&PortfolioRow) -> Result<TotalInterest, PortfolioError> + Sync,
+    {
+        let summarise = || {
+            self.rows
+                .par_iter()
+                .map(|row| LoanSummary {
+                    line: row.line,
+                    id: row.id.clone(),
+                    total_interest: calculate(row),
+                })
+                .collect()
+        };
+        match self.threads {
+            Some(threads) => match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+                Ok(pool) => pool.install(summarise),
+                Err(e) => {
+                    warn!("calculating on the global thread pool: {}", e);
+                    summarise()
+                }
+            },
+            None => summarise(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    // Writes `contents` to a file in the temporary directory, named after
+    // the process so that concurrent test runs do not share files
+    fn portfolio_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oneiro-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    // Each summary's id and either its total interest or its error
+    fn outcomes(summaries: &[LoanSummary]) -> Vec<(&str, Result<String, String>)> {
+        summaries
+            .iter()
+            .map(|summary| {
+                let outcome = match &summary.total_interest {
+                    Ok(total_interest) => Ok(total_interest.with_margin.rounded().to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                (summary.id.as_str(), outcome)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rows_that_cannot_be_read_or_calculated_fail_alone() {
+        let path = portfolio_file(
+            "rows.csv",
+            "id,start_date,end_date,loan_amount,currency,base_rate,margin,day_count\n\
+             good,2024-01-01,2024-12-31,1000,GBP,5,1,ACT/365F\n\
+             bad-date,2024-13-01,2024-12-31,1000,GBP,5,1,\n\
+             ,2024-01-01,2024-12-31,1000,ABC,5,1,\n\
+             inverted,2024-12-31,2024-01-01,1000,GBP,5,1,\n\
+             no-margin,2024-01-01,2024-12-31,1000,GBP,5,,\n",
+        );
+        let portfolio = Portfolio::from_file(&path).unwrap();
+        assert_eq!(
+            portfolio
+                .rows
+                .iter()
+                .map(|row| row.line)
+                .collect::<Vec<_>>(),
+            [2, 3, 4, 5, 6]
+        );
+        let summaries = portfolio.total_interest(&RateLimits::default());
+        let outcomes = outcomes(&summaries);
+        // 366 days of 1,000 at 6% ACT/365F, each rounded to 0.16
+        assert_eq!(outcomes[0], ("good", Ok("58.56".into())));
+        assert_eq!(outcomes[1].0, "bad-date");
+        assert!(outcomes[1]
+            .1
+            .as_ref()
+            .unwrap_err()
+            .starts_with("Error in portfolio on line 3: invalid start_date \"2024-13-01\""));
+        assert_eq!(
+            outcomes[2],
+            (
+                "4",
+                Err("Error in portfolio on line 4: Error unknown currency code: ABC".into())
+            )
+        );
+        assert!(outcomes[3]
+            .1
+            .as_ref()
+            .unwrap_err()
+            .starts_with("Error in portfolio on line 5: "));
+        assert_eq!(
+            outcomes[4],
+            (
+                "no-margin",
+                Err("Error in portfolio on line 6: missing margin".into())
+            )
+        );
+    }
+
+    #[test]
+    fn schedule_callback_errors_fail_their_loan_alone() {
+        let path = portfolio_file(
+            "callback.csv",
+            "id,start_date,end_date,loan_amount,currency,base_rate,margin\n\
+             a,2024-01-01,2024-01-31,1000,GBP,5,1\n\
+             b,2024-01-01,2024-01-31,1000,GBP,5,1\n",
+        );
+        let portfolio = Portfolio::from_file(&path).unwrap();
+        let summaries = portfolio.calculate(&RateLimits::default(), |row, _| {
+            if row.id == "b" {
+                Err("disk full".into())
+            } else {
+                Ok(())
+            }
+        });
+        assert!(summaries[0].total_interest.is_ok());
+        assert_eq!(
+            summaries[1]
+                .total_interest
+                .as_ref()
+                .unwrap_err()
+                .to_string(),
+            "Error in portfolio on line 3: disk full"
+        );
+    }
+
+    #[test]
+    fn jsonl_rows_take_their_id_or_line() {
+        let path = portfolio_file(
+            "rows.jsonl",
+            r#"{"id": "a", "start_date": "2024-01-01", "end_date": "2024-01-31", "loan_amount": "1000", "base_rate": "5", "margin": "1", "currency": "GBP"}
+
+{"id": 7, "start_date": "2024-01-01", "end_date": "2024-01-31", "loan_amount": "1000", "base_rate": "5", "margin": "1", "currency": "GBP"}
+{"start_date": "2024-01-01", "end_date": "2024-01-31", "loan_amount": "1000", "base_rate": "5", "margin": "1", "currency": "GBP"}
+{"id": "broken", "start_date": "2024-01-01"}
+"#,
+        );
+        let portfolio = Portfolio::from_file(&path).unwrap();
+        assert_eq!(
+            portfolio
+                .rows
+                .iter()
+                .map(|row| (row.line, row.id.as_str(), row.loan.is_ok()))
+                .collect::<Vec<_>>(),
+            [
+                (1, "a", true),
+                (3, "7", true),
+                (4, "4", true),
+                (5, "broken", false)
+            ]
+        );
+        assert_eq!(portfolio.rows[3].loan.as_ref().unwrap_err().line(), 5);
+    }
+
+    // Loans whose terms get shorter down the book, so that on several
+    // threads later rows finish before earlier ones
+    fn book(loans: u64) -> Portfolio {
+        let start_date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        Portfolio::new(
+            (0..loans)
+                .map(|index| {
+                    let years = (loans - index) as u32;
+                    let loan = Loan::new(
+                        start_date,
+                        start_date + chrono::Months::new(12 * years),
+                        Decimal::from(10_000 * (index + 1)),
+                        RateSchedule::flat(Decimal::from(5)),
+                        Decimal::from(1),
+                        CurrencyCode::GBP,
+                    )
+                    .with_repayment(RepaymentProfile::Annuity, Some(Frequency::Monthly));
+                    PortfolioRow {
+                        line: index + 2,
+                        id: format!("loan-{}", index),
+                        loan: Ok(loan),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn summaries_keep_the_row_order_whatever_the_thread_count() {
+        let limits = RateLimits::default();
+        let single = book(32).with_threads(1).total_interest(&limits);
+        let ids = |summaries: &[LoanSummary]| {
+            summaries
+                .iter()
+                .map(|summary| (summary.line, summary.id.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(&single),
+            (0..32)
+                .map(|index| (index + 2, format!("loan-{}", index)))
+                .collect::<Vec<_>>()
+        );
+        for threads in [Some(2), Some(8), None] {
+            let mut portfolio = book(32);
+            portfolio.threads = threads;
+            let summaries = portfolio.total_interest(&limits);
+            assert_eq!(ids(&summaries), ids(&single));
+            assert_eq!(outcomes(&summaries), outcomes(&single));
+        }
+    }
+
+    #[test]
+    fn schedules_and_totals_summarise_the_same() {
+        let limits = RateLimits::default();
+        let portfolio = book(8).with_threads(4);
+        let schedules = portfolio.calculate(&limits, |_, _| Ok(()));
+        let totals = portfolio.total_interest(&limits);
+        assert_eq!(outcomes(&schedules), outcomes(&totals));
+        assert!(totals.iter().all(|summary| summary.total_interest.is_ok()));
+    }
+
+    #[test]
+    fn files_that_cannot_be_read_fail_as_a_whole() {
+        let unknown_column = portfolio_file(
+            "unknown-column.csv",
+            "id,start_date,end_date,loan_amount,currency,base_rate,margin,fee\n",
+        );
+        assert_eq!(
+            Portfolio::from_file(&unknown_column)
+                .unwrap_err()
+                .to_string(),
+            "Error in portfolio on line 1: unknown column \"fee\""
+        );
+        let unknown_format = portfolio_file("loans.xlsx", "");
+        assert_eq!(Portfolio::from_file(&unknown_format).unwrap_err().line(), 0);
+    }
+}
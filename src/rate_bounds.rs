@@ -0,0 +1,172 @@
+use std::{error::Error, fmt::Display};
+
+use rust_decimal::Decimal;
+
+/// What the floor is compared against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FloorBasis {
+    /// The floor applies to the base rate alone
+    #[default]
+    #[cfg_attr(feature = "serde", serde(rename = "base"))]
+    BaseRate,
+    /// The floor applies to the base rate plus margin, so the base rate is
+    /// floored at the floor less the margin
+    #[cfg_attr(feature = "serde", serde(rename = "all-in"))]
+    AllIn,
+}
+
+impl Display for FloorBasis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FloorBasis::BaseRate => f.write_str("base"),
+            FloorBasis::AllIn => f.write_str("all-in"),
+        }
+    }
+}
+
+/// Floor basis that is not recognised
+#[derive(Debug)]
+pub struct UnknownFloorBasisError {
+    floor_basis: String,
+}
+
+impl UnknownFloorBasisError {
+    fn new(floor_basis: String) -> Self {
+        Self { floor_basis }
+    }
+}
+
+impl Display for UnknownFloorBasisError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error unknown floor basis: {}",
+            self.floor_basis
+        ))
+    }
+}
+
+impl Error for UnknownFloorBasisError {}
+
+impl TryFrom<&str> for FloorBasis {
+    type Error = UnknownFloorBasisError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "base" => Ok(FloorBasis::BaseRate),
+            "all-in" | "allin" => Ok(FloorBasis::AllIn),
+            _ => Err(UnknownFloorBasisError::new(value.into())),
+        }
+    }
+}
+
+/// Floor and cap on the base rate, both together form a collar
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RateBounds {
+    /// Lowest rate, as a percentage
+    pub floor: Option<Decimal>,
+    /// Highest base rate, as a percentage
+    pub cap: Option<Decimal>,
+    /// What the floor is compared against
+    pub floor_basis: FloorBasis,
+}
+
+impl RateBounds {
+    /// Whether there is a floor or a cap
+    pub fn is_bounded(&self) -> bool {
+        self.floor.is_some() || self.cap.is_some()
+    }
+
+    /// The base rate after the floor and cap, before the margin is added
+    pub fn apply(&self, base_rate: Decimal, margin: Decimal) -> Decimal {
+        let floored = match (self.floor, self.floor_basis) {
+            (Some(floor), FloorBasis::BaseRate) => base_rate.max(floor),
+            (Some(floor), FloorBasis::AllIn) => base_rate.max(floor - margin),
+            (None, _) => base_rate,
+        };
+        match self.cap {
+            Some(cap) => floored.min(cap),
+            None => floored,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn percent(rate: i64) -> Decimal {
+        Decimal::new(rate, 2)
+    }
+
+    fn bounds(floor: Option<i64>, cap: Option<i64>, floor_basis: FloorBasis) -> RateBounds {
+        RateBounds {
+            floor: floor.map(percent),
+            cap: cap.map(percent),
+            floor_basis,
+        }
+    }
+
+    #[test]
+    fn unbounded_rates_are_unchanged() {
+        let unbounded = RateBounds::default();
+        assert!(!unbounded.is_bounded());
+        assert_eq!(unbounded.apply(percent(-50), percent(200)), percent(-50));
+    }
+
+    #[test]
+    fn floors_raise_base_rates_below_them() {
+        let floor = bounds(Some(0), None, FloorBasis::BaseRate);
+        assert!(floor.is_bounded());
+        assert_eq!(floor.apply(percent(-25), percent(200)), percent(0));
+        assert_eq!(floor.apply(percent(0), percent(200)), percent(0));
+        assert_eq!(floor.apply(percent(150), percent(200)), percent(150));
+    }
+
+    #[test]
+    fn caps_lower_base_rates_above_them() {
+        let cap = bounds(None, Some(500), FloorBasis::BaseRate);
+        assert!(cap.is_bounded());
+        assert_eq!(cap.apply(percent(650), percent(200)), percent(500));
+        assert_eq!(cap.apply(percent(500), percent(200)), percent(500));
+        assert_eq!(cap.apply(percent(450), percent(200)), percent(450));
+    }
+
+    #[test]
+    fn collars_clamp_base_rates_between_floor_and_cap() {
+        let collar = bounds(Some(100), Some(500), FloorBasis::BaseRate);
+        assert_eq!(collar.apply(percent(50), percent(200)), percent(100));
+        assert_eq!(collar.apply(percent(300), percent(200)), percent(300));
+        assert_eq!(collar.apply(percent(700), percent(200)), percent(500));
+    }
+
+    #[test]
+    fn all_in_floors_floor_the_base_rate_at_the_floor_less_the_margin() {
+        // A 1% all-in floor with a 2% margin only bites below -1%
+        let floor = bounds(Some(100), Some(500), FloorBasis::AllIn);
+        assert_eq!(floor.apply(percent(-150), percent(200)), percent(-100));
+        assert_eq!(floor.apply(percent(-50), percent(200)), percent(-50));
+        assert_eq!(floor.apply(percent(700), percent(200)), percent(500));
+        // The same floor on the base rate alone bites below 1%
+        let floor = bounds(Some(100), Some(500), FloorBasis::BaseRate);
+        assert_eq!(floor.apply(percent(-50), percent(200)), percent(100));
+    }
+
+    #[test]
+    fn floor_bases_parse_and_display() {
+        for floor_basis in [FloorBasis::BaseRate, FloorBasis::AllIn] {
+            assert_eq!(
+                FloorBasis::try_from(floor_basis.to_string().as_str()).unwrap(),
+                floor_basis
+            );
+        }
+        assert_eq!(FloorBasis::try_from("AllIn").unwrap(), FloorBasis::AllIn);
+        assert_eq!(
+            FloorBasis::try_from("margin").unwrap_err().to_string(),
+            "Error unknown floor basis: margin"
+        );
+    }
+}
@@ -0,0 +1,375 @@
+use std::{error::Error, fmt::Display, path::Path, str::FromStr};
+
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+
+/// A base rate that applies from its effective date until the next fixing
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Fixing {
+    /// First day the rate applies
+    pub effective_date: NaiveDate,
+    /// Rate as a percentage
+    pub rate: Decimal,
+}
+
+/// Series of base rate fixings, kept sorted by effective date. Serialized as
+/// a list of fixings, or as the rate alone for a flat schedule.
+#[derive(Debug, Clone)]
+pub struct RateSchedule {
+    fixings: Vec<Fixing>,
+}
+
+/// Error reading a base rate file, with the line it occurred on
+#[derive(Debug)]
+pub struct RateScheduleError {
+    line: u64,
+    message: String,
+}
+
+impl RateScheduleError {
+    fn new(line: u64, message: String) -> Self {
+        Self { line, message }
+    }
+
+    /// Line of the file the error occurred on, 0 when it is not tied to a line
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+}
+
+impl Display for RateScheduleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error reading base rates on line {}: {}",
+            self.line, self.message
+        ))
+    }
+}
+
+impl Error for RateScheduleError {}
+
+/// A flat schedule is written as its rate, so no date outside the range of
+/// the formats is written
+#[cfg(feature = "serde")]
+impl serde::Serialize for RateSchedule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self.flat_rate() {
+            Some(rate) => serde::Serialize::serialize(&rate, serializer),
+            None => self.fixings.serialize(serializer),
+        }
+    }
+}
+
+/// Reads a list of fixings in any order, sorting them as `RateSchedule::new`
+/// does, or a single rate as `RateSchedule::flat`
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RateSchedule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(RateScheduleVisitor)
+    }
+}
+
+#[cfg(feature = "serde")]
+struct RateScheduleVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for RateScheduleVisitor {
+    type Value = RateSchedule;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("a rate or a list of fixings")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Decimal::from_str(value.trim())
+            .map(RateSchedule::flat)
+            .map_err(E::custom)
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(RateSchedule::flat(Decimal::from(value)))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(RateSchedule::flat(Decimal::from(value)))
+    }
+
+    // Read from the shortest text of the float, so 5.1 is 5.1 rather than
+    // its binary approximation
+    fn visit_f64<E>(self, value: f64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        self.visit_str(&value.to_string())
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut fixings = Vec::new();
+        while let Some(fixing) = seq.next_element()? {
+            fixings.push(fixing);
+        }
+        Ok(RateSchedule::new(fixings))
+    }
+}
+
+/// A rate or a list of fixings, as the schedule is serialized
+#[cfg(feature = "schema")]
+impl schemars::JsonSchema for RateSchedule {
+    fn schema_name() -> String {
+        "RateSchedule".into()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{Metadata, SchemaObject, SubschemaValidation};
+
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "Base rate fixings in any order, or a single rate that applies on every date"
+                        .into(),
+                ),
+                ..Metadata::default()
+            })),
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<Decimal>(),
+                    gen.subschema_for::<Vec<Fixing>>(),
+                ]),
+                ..SubschemaValidation::default()
+            })),
+            ..SchemaObject::default()
+        }
+        .into()
+    }
+}
+
+impl RateSchedule {
+    /// Schedule of `fixings` in any order
+    pub fn new(mut fixings: Vec<Fixing>) -> Self {
+        fixings.sort_by_key(|fixing| fixing.effective_date);
+        Self { fixings }
+    }
+
+    /// A single rate that applies on every date
+    pub fn flat(rate: Decimal) -> Self {
+        Self::new(vec![Fixing {
+            effective_date: NaiveDate::MIN,
+            rate,
+        }])
+    }
+
+    /// Reads fixings from a CSV file with `effective_date,rate` columns, dates
+    /// formatted as YYYY-MM-DD and rates as percentages
+    pub fn from_csv(path: &Path) -> Result<Self, RateScheduleError> {
+        let mut reader =
+            csv::Reader::from_path(path).map_err(|e| RateScheduleError::new(0, e.to_string()))?;
+        let mut fixings = Vec::new();
+        for record in reader.records() {
+            let record = record.map_err(|e| {
+                let line = e.position().map_or(0, |position| position.line());
+                RateScheduleError::new(line, e.to_string())
+            })?;
+            let line = record.position().map_or(0, |position| position.line());
+            let field = |index: usize, name: &str| {
+                record
+                    .get(index)
+                    .map(str::trim)
+                    .ok_or_else(|| RateScheduleError::new(line, format!("missing {}", name)))
+            };
+            let effective_date = NaiveDate::parse_from_str(field(0, "effective_date")?, "%Y-%m-%d")
+                .map_err(|e| RateScheduleError::new(line, e.to_string()))?;
+            let rate = Decimal::from_str(field(1, "rate")?)
+                .map_err(|e| RateScheduleError::new(line, e.to_string()))?;
+            fixings.push(Fixing {
+                effective_date,
+                rate,
+            });
+        }
+        if fixings.is_empty() {
+            return Err(RateScheduleError::new(0, "no fixings found".into()));
+        }
+        Ok(Self::new(fixings))
+    }
+
+    /// Fixings in date order
+    pub fn fixings(&self) -> &[Fixing] {
+        &self.fixings
+    }
+
+    /// The rate of a schedule built with `RateSchedule::flat`
+    pub fn flat_rate(&self) -> Option<Decimal> {
+        match self.fixings.as_slice() {
+            [fixing] if fixing.effective_date == NaiveDate::MIN => Some(fixing.rate),
+            _ => None,
+        }
+    }
+
+    /// The rate of the latest fixing effective on or before `date`
+    pub fn rate_on(&self, date: NaiveDate) -> Option<Decimal> {
+        let index = self
+            .fixings
+            .partition_point(|fixing| fixing.effective_date <= date);
+        index.checked_sub(1).map(|index| self.fixings[index].rate)
+    }
+
+    /// Effective date of the first fixing after `date`
+    pub(crate) fn next_fixing(&self, date: NaiveDate) -> Option<NaiveDate> {
+        let index = self
+            .fixings
+            .partition_point(|fixing| fixing.effective_date <= date);
+        self.fixings.get(index).map(|fixing| fixing.effective_date)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn fixing(effective_date: NaiveDate, rate: i64) -> Fixing {
+        Fixing {
+            effective_date,
+            rate: Decimal::new(rate, 2),
+        }
+    }
+
+    // Writes `contents` to a file in the temporary directory, named after
+    // the process so that concurrent test runs do not share files
+    fn rates_file(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("oneiro-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn fixings_are_sorted_by_effective_date() {
+        let schedule = RateSchedule::new(vec![
+            fixing(date(2023, 7, 1), 450),
+            fixing(date(2023, 1, 1), 400),
+            fixing(date(2023, 4, 1), 425),
+        ]);
+        assert_eq!(
+            schedule
+                .fixings()
+                .iter()
+                .map(|fixing| fixing.effective_date)
+                .collect::<Vec<_>>(),
+            [date(2023, 1, 1), date(2023, 4, 1), date(2023, 7, 1)]
+        );
+    }
+
+    #[test]
+    fn rates_apply_from_their_effective_date_until_the_next_fixing() {
+        let schedule = RateSchedule::new(vec![
+            fixing(date(2023, 1, 1), 400),
+            fixing(date(2023, 4, 1), 425),
+        ]);
+        assert_eq!(schedule.rate_on(date(2022, 12, 31)), None);
+        assert_eq!(
+            schedule.rate_on(date(2023, 1, 1)),
+            Some(Decimal::new(400, 2))
+        );
+        assert_eq!(
+            schedule.rate_on(date(2023, 3, 31)),
+            Some(Decimal::new(400, 2))
+        );
+        assert_eq!(
+            schedule.rate_on(date(2023, 4, 1)),
+            Some(Decimal::new(425, 2))
+        );
+        assert_eq!(
+            schedule.rate_on(date(2030, 1, 1)),
+            Some(Decimal::new(425, 2))
+        );
+    }
+
+    #[test]
+    fn next_fixing_is_strictly_after_the_date() {
+        let schedule = RateSchedule::new(vec![
+            fixing(date(2023, 1, 1), 400),
+            fixing(date(2023, 4, 1), 425),
+        ]);
+        assert_eq!(
+            schedule.next_fixing(date(2022, 6, 1)),
+            Some(date(2023, 1, 1))
+        );
+        assert_eq!(
+            schedule.next_fixing(date(2023, 1, 1)),
+            Some(date(2023, 4, 1))
+        );
+        assert_eq!(schedule.next_fixing(date(2023, 4, 1)), None);
+    }
+
+    #[test]
+    fn flat_schedules_apply_on_every_date() {
+        let schedule = RateSchedule::flat(Decimal::new(525, 2));
+        assert_eq!(schedule.rate_on(NaiveDate::MIN), Some(Decimal::new(525, 2)));
+        assert_eq!(schedule.next_fixing(date(2023, 1, 1)), None);
+        assert_eq!(schedule.flat_rate(), Some(Decimal::new(525, 2)));
+        assert_eq!(
+            RateSchedule::new(vec![fixing(date(2023, 1, 1), 400)]).flat_rate(),
+            None
+        );
+    }
+
+    #[test]
+    fn csv_fixings_are_read_in_any_order() {
+        let path = rates_file(
+            "fixings.csv",
+            "effective_date,rate\n2023-04-01, 4.25\n2023-01-01,4.00\n",
+        );
+        let schedule = RateSchedule::from_csv(&path).unwrap();
+        assert_eq!(schedule.fixings()[0].effective_date, date(2023, 1, 1));
+        assert_eq!(
+            schedule.rate_on(date(2023, 5, 1)),
+            Some(Decimal::new(425, 2))
+        );
+    }
+
+    #[test]
+    fn csv_errors_name_their_line() {
+        let path = rates_file(
+            "bad-rate.csv",
+            "effective_date,rate\n2023-01-01,4.00\n2023-04-01,high\n",
+        );
+        let error = RateSchedule::from_csv(&path).unwrap_err();
+        assert_eq!(error.line(), 3);
+        assert!(error
+            .to_string()
+            .starts_with("Error reading base rates on line 3: "));
+
+        let path = rates_file("bad-date.csv", "effective_date,rate\n01/01/2023,4.00\n");
+        assert_eq!(RateSchedule::from_csv(&path).unwrap_err().line(), 2);
+
+        let path = rates_file("empty.csv", "effective_date,rate\n");
+        assert_eq!(
+            RateSchedule::from_csv(&path).unwrap_err().to_string(),
+            "Error reading base rates on line 0: no fixings found"
+        );
+    }
+}
@@ -0,0 +1,265 @@
+use std::{error::Error, fmt::Display};
+
+use rust_decimal::{Decimal, MathematicalOps};
+
+/// How the principal of a loan is repaid over its payment dates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(tag = "type"))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum RepaymentProfile {
+    /// All principal is repaid on the final payment date
+    #[default]
+    Bullet,
+    /// Equal instalments of principal and interest
+    Annuity,
+    /// Equal amounts of principal, straight-line amortisation
+    Linear,
+    /// Equal instalments that amortise down to a residual, given as a
+    /// percentage of the original principal, repaid on the final payment date
+    Balloon {
+        /// Principal left for the final payment, as a percentage of the
+        /// original principal
+        residual_percentage: Decimal,
+    },
+}
+
+impl Display for RepaymentProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RepaymentProfile::Bullet => f.write_str("bullet"),
+            RepaymentProfile::Annuity => f.write_str("annuity"),
+            RepaymentProfile::Linear => f.write_str("linear"),
+            RepaymentProfile::Balloon {
+                residual_percentage,
+            } => f.write_fmt(format_args!("balloon ({}%)", residual_percentage)),
+        }
+    }
+}
+
+/// Repayment profile that is not recognised
+#[derive(Debug)]
+pub struct UnknownRepaymentError {
+    repayment: String,
+}
+
+impl UnknownRepaymentError {
+    fn new(repayment: String) -> Self {
+        Self { repayment }
+    }
+}
+
+impl Display for UnknownRepaymentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error unknown repayment profile: {}",
+            self.repayment
+        ))
+    }
+}
+
+impl Error for UnknownRepaymentError {}
+
+impl RepaymentProfile {
+    /// Parses a profile name, balloon loans take their residual separately
+    pub fn parse(value: &str, residual_percentage: Decimal) -> Result<Self, UnknownRepaymentError> {
+        match value.to_lowercase().as_str() {
+            "bullet" => Ok(RepaymentProfile::Bullet),
+            "annuity" => Ok(RepaymentProfile::Annuity),
+            "linear" | "straight-line" => Ok(RepaymentProfile::Linear),
+            "balloon" => Ok(RepaymentProfile::Balloon {
+                residual_percentage,
+            }),
+            _ => Err(UnknownRepaymentError::new(value.into())),
+        }
+    }
+
+    /// Principal due on a payment date, before rounding.
+    ///
+    /// `balance` is the outstanding principal, `interest` the interest paid on
+    /// the same date, `periodic_rate` the all-in rate for one payment period
+    /// as a fraction and `remaining_payments` includes this payment.
+    pub fn principal_due(
+        &self,
+        balance: Decimal,
+        original_principal: Decimal,
+        interest: Decimal,
+        periodic_rate: Decimal,
+        remaining_payments: u32,
+    ) -> Decimal {
+        if remaining_payments <= 1 {
+            return balance;
+        }
+        let principal = match self {
+            RepaymentProfile::Bullet => Decimal::ZERO,
+            RepaymentProfile::Linear => balance / Decimal::from(remaining_payments),
+            RepaymentProfile::Annuity => {
+                instalment(balance, Decimal::ZERO, periodic_rate, remaining_payments) - interest
+            }
+            RepaymentProfile::Balloon {
+                residual_percentage,
+            } => {
+                let residual = original_principal * residual_percentage / Decimal::from(100);
+                instalment(balance, residual, periodic_rate, remaining_payments) - interest
+            }
+        };
+        principal.max(Decimal::ZERO).min(balance)
+    }
+}
+
+// Level instalment that repays `balance` down to `residual` over `payments`
+// periods at `rate` per period
+fn instalment(balance: Decimal, residual: Decimal, rate: Decimal, payments: u32) -> Decimal {
+    if rate.is_zero() {
+        return (balance - residual) / Decimal::from(payments);
+    }
+    let discount = (Decimal::ONE + rate).powi(-(payments as i64));
+    (balance - residual * discount) * rate / (Decimal::ONE - discount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pays `payments` periods of interest at `rate` on 100,000 with the
+    // profile, giving each payment's principal and interest
+    fn amortise(
+        profile: RepaymentProfile,
+        rate: Decimal,
+        payments: u32,
+    ) -> Vec<(Decimal, Decimal)> {
+        let original_principal = Decimal::from(100_000);
+        let mut balance = original_principal;
+        (0..payments)
+            .map(|payment| {
+                let interest = balance * rate;
+                let principal = profile.principal_due(
+                    balance,
+                    original_principal,
+                    interest,
+                    rate,
+                    payments - payment,
+                );
+                balance -= principal;
+                (principal, interest)
+            })
+            .collect()
+    }
+
+    fn cents(value: Decimal) -> Decimal {
+        value.round_dp(2)
+    }
+
+    #[test]
+    fn annuities_pay_level_instalments() {
+        let payments = amortise(RepaymentProfile::Annuity, Decimal::new(1, 2), 12);
+        // 100,000 * 0.01 / (1 - 1.01^-12)
+        for (principal, interest) in &payments {
+            assert_eq!(cents(principal + interest), Decimal::new(888_488, 2));
+        }
+        let repaid: Decimal = payments.iter().map(|(principal, _)| principal).sum();
+        assert_eq!(cents(repaid), Decimal::from(100_000));
+        // The first payment is mostly interest, the last mostly principal
+        assert_eq!(cents(payments[0].0), Decimal::new(788_488, 2));
+        assert_eq!(cents(payments[11].1), Decimal::new(8_797, 2));
+    }
+
+    #[test]
+    fn balloons_pay_level_instalments_down_to_the_residual() {
+        let balloon = RepaymentProfile::Balloon {
+            residual_percentage: Decimal::from(20),
+        };
+        let payments = amortise(balloon, Decimal::new(1, 2), 12);
+        // (100,000 - 20,000 * 1.01^-12) * 0.01 / (1 - 1.01^-12)
+        for (principal, interest) in &payments[..11] {
+            assert_eq!(cents(principal + interest), Decimal::new(730_790, 2));
+        }
+        // The last instalment is the level one plus the residual
+        let (principal, interest) = payments[11];
+        assert_eq!(cents(principal + interest), Decimal::new(2_730_790, 2));
+        let repaid: Decimal = payments.iter().map(|(principal, _)| principal).sum();
+        assert_eq!(cents(repaid), Decimal::from(100_000));
+    }
+
+    #[test]
+    fn zero_rates_repay_in_equal_parts() {
+        let payments = amortise(RepaymentProfile::Annuity, Decimal::ZERO, 4);
+        assert!(payments
+            .iter()
+            .all(|payment| *payment == (Decimal::from(25_000), Decimal::ZERO)));
+
+        let balloon = RepaymentProfile::Balloon {
+            residual_percentage: Decimal::from(20),
+        };
+        let payments = amortise(balloon, Decimal::ZERO, 4);
+        assert_eq!(payments[0].0, Decimal::from(20_000));
+        assert_eq!(payments[3].0, Decimal::from(40_000));
+    }
+
+    #[test]
+    fn linear_loans_repay_equal_principal() {
+        let payments = amortise(RepaymentProfile::Linear, Decimal::new(1, 2), 4);
+        assert!(payments
+            .iter()
+            .all(|(principal, _)| *principal == Decimal::from(25_000)));
+        assert_eq!(payments[0].1, Decimal::from(1_000));
+        assert_eq!(payments[3].1, Decimal::from(250));
+    }
+
+    #[test]
+    fn bullets_repay_everything_on_the_last_payment() {
+        let payments = amortise(RepaymentProfile::Bullet, Decimal::new(1, 2), 4);
+        assert!(payments[..3]
+            .iter()
+            .all(|(principal, _)| principal.is_zero()));
+        assert_eq!(payments[3].0, Decimal::from(100_000));
+    }
+
+    #[test]
+    fn principal_due_never_exceeds_the_balance_or_goes_negative() {
+        // Interest above the instalment would make the principal negative
+        let annuity = RepaymentProfile::Annuity.principal_due(
+            Decimal::from(1_000),
+            Decimal::from(1_000),
+            Decimal::from(500),
+            Decimal::new(1, 2),
+            12,
+        );
+        assert_eq!(annuity, Decimal::ZERO);
+        // A residual above the balance would too
+        let balloon = RepaymentProfile::Balloon {
+            residual_percentage: Decimal::from(90),
+        }
+        .principal_due(
+            Decimal::from(500),
+            Decimal::from(1_000),
+            Decimal::from(5),
+            Decimal::new(1, 2),
+            6,
+        );
+        assert_eq!(balloon, Decimal::ZERO);
+    }
+
+    #[test]
+    fn profiles_parse_with_their_residual() {
+        assert_eq!(
+            RepaymentProfile::parse("Straight-Line", Decimal::ZERO).unwrap(),
+            RepaymentProfile::Linear
+        );
+        let balloon = RepaymentProfile::parse("balloon", Decimal::from(30)).unwrap();
+        assert_eq!(
+            balloon,
+            RepaymentProfile::Balloon {
+                residual_percentage: Decimal::from(30)
+            }
+        );
+        assert_eq!(balloon.to_string(), "balloon (30%)");
+        assert_eq!(
+            RepaymentProfile::parse("interest-only", Decimal::ZERO)
+                .unwrap_err()
+                .to_string(),
+            "Error unknown repayment profile: interest-only"
+        );
+    }
+}
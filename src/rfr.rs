@@ -0,0 +1,473 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::{Duration, NaiveDate};
+use rust_decimal::Decimal;
+
+use crate::rate_schedule::RateSchedule;
+
+/// How overnight fixings are observed for each day of an interest period
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum ObservationMethod {
+    /// Fixings are taken `lookback_days` business days earlier but weighted
+    /// by the calendar days of the interest period
+    Lookback,
+    /// The observation period is shifted back `lookback_days` business days
+    /// and fixings are weighted by the calendar days of the observation period
+    ObservationShift,
+}
+
+/// Terms of a loan that accrues a risk free rate (SONIA, SOFR, €STR)
+/// compounded in arrears
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RfrTerms {
+    /// How fixings are observed
+    pub method: ObservationMethod,
+    /// Business days the observation is shifted back
+    pub lookback_days: usize,
+    /// Business days at the end of the interest period that reuse the fixing
+    /// observed before them
+    pub lockout_days: usize,
+    /// Business days between the end of the interest period and payment
+    pub payment_delay_days: usize,
+    /// Days in the year the fixings are quoted on, 365 for SONIA and 360 for
+    /// SOFR and €STR, 365 when left out of a loan file
+    #[cfg_attr(feature = "serde", serde(default = "default_day_basis"))]
+    pub day_basis: u32,
+}
+
+// Loan files written before the day basis was configurable compounded on 365
+#[cfg(feature = "serde")]
+fn default_day_basis() -> u32 {
+    RfrTerms::default().day_basis
+}
+
+/// SONIA terms: a 365 day year with no lookback, lockout or payment delay
+impl Default for RfrTerms {
+    fn default() -> Self {
+        Self {
+            method: ObservationMethod::Lookback,
+            lookback_days: 0,
+            lockout_days: 0,
+            payment_delay_days: 0,
+            day_basis: 365,
+        }
+    }
+}
+
+/// Compounded rates for one calendar day of the interest period, rates are
+/// percentages
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompoundedRate {
+    /// Business day whose overnight fixing was used
+    pub observation_date: NaiveDate,
+    /// Overnight fixing observed, as a percentage
+    pub overnight_rate: Decimal,
+    /// Growth of the compounded product since the start of the period
+    pub cumulative_rate: Decimal,
+    /// Cumulative rate annualised over the days observed so far
+    pub annualised_rate: Decimal,
+    /// Annualised rate accrued on this day, the daily non-cumulative
+    /// compounded rate
+    pub daily_rate: Decimal,
+}
+
+/// Fixings that cannot be compounded over an interest period
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RfrError {
+    message: String,
+}
+
+impl RfrError {
+    fn new(message: String) -> Self {
+        Self { message }
+    }
+}
+
+impl Display for RfrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Error compounding RFR: {}", self.message))
+    }
+}
+
+impl Error for RfrError {}
+
+impl RfrTerms {
+    /// Checks that `fixings` cover the interest period `[start, end)` with
+    /// enough history for the lookback and enough business days for the lockout
+    pub fn check(
+        &self,
+        fixings: &RateSchedule,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<(), RfrError> {
+        if self.day_basis == 0 {
+            return Err(RfrError::new("a day basis of 0 days".to_string()));
+        }
+        let business_days = fixings.fixings();
+        let first = business_days.partition_point(|fixing| fixing.effective_date <= start);
+        if first == 0 {
+            return Err(RfrError::new(format!("no fixing on or before {}", start)));
+        }
+        if first - 1 < self.lookback_days {
+            return Err(RfrError::new(format!(
+                "a lookback of {} business days needs earlier fixings than {}",
+                self.lookback_days, business_days[0].effective_date
+            )));
+        }
+        let period_business_days =
+            business_days.partition_point(|fixing| fixing.effective_date < end) - (first - 1);
+        if self.lockout_days >= period_business_days {
+            return Err(RfrError::new(format!(
+                "a lockout of {} business days covers the whole interest period from {}",
+                self.lockout_days, start
+            )));
+        }
+        Ok(())
+    }
+
+    /// Compounds the overnight `fixings` over the interest period
+    /// `[start, end)` using the ISDA/LMA compounded in arrears formula,
+    /// returning one rate per calendar day. The dates of the fixings are the
+    /// business days. Each interest period is compounded on its own, the
+    /// product starting again at its first day, and the lockout applies to
+    /// its last business days. Fails when [`RfrTerms::check`] does.
+    pub fn compound(
+        &self,
+        fixings: &RateSchedule,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<CompoundedRate>, RfrError> {
+        self.check(fixings, start, end)?;
+        let business_days = fixings.fixings();
+
+        // Each business day's fixing covers the calendar days up to the next
+        // business day. Group the days of the period by that business day.
+        let mut blocks: Vec<(usize, u32)> = Vec::new();
+        let mut date = start;
+        while date < end {
+            let index = business_days.partition_point(|fixing| fixing.effective_date <= date) - 1;
+            match blocks.last_mut() {
+                Some((last, days)) if *last == index => *days += 1,
+                _ => blocks.push((index, 1)),
+            }
+            date += Duration::days(1);
+        }
+
+        let hundred = Decimal::from(100);
+        let day_basis = Decimal::from(self.day_basis);
+        let lockout_from = blocks.len() - self.lockout_days;
+        let mut factor = Decimal::ONE;
+        let mut observed_days = 0;
+        let mut rates = Vec::new();
+        for (block, &(index, days)) in blocks.iter().enumerate() {
+            let index = if block >= lockout_from {
+                blocks[lockout_from - 1].0
+            } else {
+                index
+            };
+            let fixing = business_days[index - self.lookback_days];
+            let weight = match self.method {
+                ObservationMethod::Lookback => days,
+                ObservationMethod::ObservationShift => business_days
+                    .get(index - self.lookback_days + 1)
+                    .map_or(days, |next| {
+                        (next.effective_date - fixing.effective_date).num_days() as u32
+                    }),
+            };
+
+            let previous_factor = factor;
+            factor *= Decimal::ONE + fixing.rate / hundred * Decimal::from(weight) / day_basis;
+            observed_days += weight;
+
+            let rate = CompoundedRate {
+                observation_date: fixing.effective_date,
+                overnight_rate: fixing.rate,
+                cumulative_rate: (factor - Decimal::ONE) * hundred,
+                annualised_rate: (factor - Decimal::ONE) * day_basis / Decimal::from(observed_days)
+                    * hundred,
+                daily_rate: (factor - previous_factor) * day_basis / Decimal::from(weight)
+                    * hundred,
+            };
+            rates.extend(std::iter::repeat(rate).take(days as usize));
+        }
+        Ok(rates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Datelike, Weekday};
+
+    use super::*;
+    use crate::frequency::Frequency;
+    use crate::rate_schedule::Fixing;
+    use crate::{CurrencyCode, Loan, Schedule};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    // Illustrative overnight fixings around Easter 2024, when Good Friday
+    // (29 March) and Easter Monday (1 April) were not business days. They are
+    // not the published SONIA fixings, the index below checks the
+    // compounding whatever the rates are.
+    fn easter_fixings() -> RateSchedule {
+        RateSchedule::new(
+            [
+                (date(2024, 3, 21), 51927),
+                (date(2024, 3, 22), 51931),
+                (date(2024, 3, 25), 51936),
+                (date(2024, 3, 26), 51942),
+                (date(2024, 3, 27), 51938),
+                (date(2024, 3, 28), 51944),
+                (date(2024, 4, 2), 51950),
+                (date(2024, 4, 3), 51954),
+                (date(2024, 4, 4), 51948),
+                (date(2024, 4, 5), 51952),
+                (date(2024, 4, 8), 51946),
+                (date(2024, 4, 9), 51951),
+            ]
+            .into_iter()
+            .map(|(effective_date, rate)| Fixing {
+                effective_date,
+                rate: Decimal::new(rate, 4),
+            })
+            .collect(),
+        )
+    }
+
+    fn sonia(method: ObservationMethod, lookback_days: usize, lockout_days: usize) -> RfrTerms {
+        RfrTerms {
+            method,
+            lookback_days,
+            lockout_days,
+            payment_delay_days: 0,
+            day_basis: 365,
+        }
+    }
+
+    // Compounds the interest period from Tuesday 2 April to Tuesday 9 April
+    // 2024, 7 calendar days over 5 business days
+    fn easter_week(rfr: RfrTerms) -> Vec<CompoundedRate> {
+        let fixings = easter_fixings();
+        let (start, end) = (date(2024, 4, 2), date(2024, 4, 9));
+        rfr.compound(&fixings, start, end).unwrap()
+    }
+
+    // The SONIA Compounded Index built as the Bank of England builds the one
+    // it publishes: each business day's index is the previous one times
+    // (1 + r n / 36500) for its fixing r in percent over the n calendar days
+    // to the next business day, rounded to 8 decimal places. The published
+    // index started at 100 on 23 April 2018, this one starts at 100 on
+    // `from` as only ratios are compared. Returns the index on each business
+    // day from `from` to `to`.
+    fn sonia_index(
+        fixings: &RateSchedule,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Vec<(NaiveDate, Decimal)> {
+        let business_days: Vec<&Fixing> = fixings
+            .fixings()
+            .iter()
+            .filter(|fixing| from <= fixing.effective_date && fixing.effective_date <= to)
+            .collect();
+        let mut index = vec![(from, Decimal::from(100))];
+        for pair in business_days.windows(2) {
+            let days = (pair[1].effective_date - pair[0].effective_date).num_days();
+            let previous = index.last().unwrap().1;
+            let next = previous
+                * (Decimal::ONE + pair[0].rate * Decimal::from(days) / Decimal::from(36500));
+            index.push((pair[1].effective_date, next.round_dp(8)));
+        }
+        index
+    }
+
+    // Compounded rate between two business days from the index, as the Bank
+    // of England's SONIA Compounded Index key features and policies define
+    // it: (index on the end date / index on the start date - 1) times 365
+    // over the calendar days between them, rounded to four decimal places.
+    // The growth of the index between the dates, in percent, is checked to
+    // six places as the index is only kept to eight.
+    fn assert_index_rate(rates: &[CompoundedRate], from: NaiveDate, to: NaiveDate) {
+        let index = sonia_index(&easter_fixings(), from, to);
+        let growth = (index.last().unwrap().1 / index[0].1 - Decimal::ONE) * Decimal::from(100);
+        let days = Decimal::from((to - from).num_days());
+        let last = rates.last().unwrap();
+        assert_eq!(
+            last.annualised_rate.round_dp(4),
+            (growth * Decimal::from(365) / days).round_dp(4)
+        );
+        assert_eq!(last.cumulative_rate.round_dp(6), growth.round_dp(6));
+    }
+
+    // The lookback without a shift and the lockout have no published index,
+    // their expected rates are the ISDA formula worked by hand: compounded
+    // (1 + r n / 365) over the business days and annualised over the days
+    // observed, the compounded rate to four decimal places as SONIA is
+    // published and the period's growth to eight
+    fn assert_compounded(rates: &[CompoundedRate], annualised: i64, cumulative: i64) {
+        let last = rates.last().unwrap();
+        assert_eq!(
+            last.annualised_rate.round_dp(4),
+            Decimal::new(annualised, 4)
+        );
+        assert_eq!(
+            last.cumulative_rate.round_dp(8),
+            Decimal::new(cumulative, 8)
+        );
+    }
+
+    fn observation_dates(rates: &[CompoundedRate]) -> Vec<NaiveDate> {
+        rates.iter().map(|rate| rate.observation_date).collect()
+    }
+
+    #[test]
+    fn compounds_each_business_day_over_the_calendar_days_it_covers() {
+        let rates = easter_week(sonia(ObservationMethod::Lookback, 0, 0));
+        assert_eq!(rates.len(), 7);
+        // Friday's fixing covers the weekend
+        assert_eq!(
+            observation_dates(&rates),
+            [
+                date(2024, 4, 2),
+                date(2024, 4, 3),
+                date(2024, 4, 4),
+                date(2024, 4, 5),
+                date(2024, 4, 5),
+                date(2024, 4, 5),
+                date(2024, 4, 8),
+            ]
+        );
+        assert_eq!(rates[0].annualised_rate.round_dp(4), Decimal::new(51950, 4));
+        assert_index_rate(&rates, date(2024, 4, 2), date(2024, 4, 9));
+    }
+
+    #[test]
+    fn lookback_observes_earlier_fixings_weighted_by_the_interest_period() {
+        let rates = easter_week(sonia(ObservationMethod::Lookback, 5, 0));
+        // Five business days before 2 April skips the Easter holidays
+        assert_eq!(rates[0].observation_date, date(2024, 3, 22));
+        assert_eq!(rates[3].observation_date, date(2024, 3, 27));
+        assert_eq!(rates[5].observation_date, date(2024, 3, 27));
+        assert_eq!(rates[6].observation_date, date(2024, 3, 28));
+        assert_compounded(&rates, 51957, 9964385);
+    }
+
+    #[test]
+    fn observation_shift_weights_fixings_by_the_observation_period() {
+        let rates = easter_week(sonia(ObservationMethod::ObservationShift, 5, 0));
+        assert_eq!(rates[6].observation_date, date(2024, 3, 28));
+        // 22 to 28 March is observed over the 11 days to 2 April, 28 March
+        // for the five days over Easter, which is the index between the
+        // shifted dates
+        assert_index_rate(&rates, date(2024, 3, 22), date(2024, 4, 2));
+    }
+
+    #[test]
+    fn lockout_repeats_the_fixing_before_the_last_business_days() {
+        let rates = easter_week(sonia(ObservationMethod::Lookback, 0, 2));
+        assert_eq!(observation_dates(&rates[2..]), [date(2024, 4, 4); 5]);
+        assert_compounded(&rates, 51968, 9966496);
+    }
+
+    #[test]
+    fn check_rejects_fixings_that_cannot_be_compounded() {
+        let fixings = easter_fixings();
+        let (start, end) = (date(2024, 4, 2), date(2024, 4, 9));
+        assert!(sonia(ObservationMethod::Lookback, 7, 0)
+            .check(&fixings, start, end)
+            .is_err());
+        assert!(sonia(ObservationMethod::Lookback, 0, 5)
+            .check(&fixings, start, end)
+            .is_err());
+        assert!(sonia(ObservationMethod::Lookback, 0, 0)
+            .check(&fixings, date(2024, 3, 1), end)
+            .is_err());
+        let no_basis = RfrTerms {
+            day_basis: 0,
+            ..sonia(ObservationMethod::Lookback, 0, 0)
+        };
+        assert!(no_basis.check(&fixings, start, end).is_err());
+    }
+
+    // A monthly SONIA loan, fixed at 5% plus a hundredth for each day of the
+    // month so every fixing differs
+    fn monthly_loan() -> Loan {
+        let mut fixings = Vec::new();
+        let mut day = date(2023, 12, 1);
+        while day <= date(2024, 4, 30) {
+            if !matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+                fixings.push(Fixing {
+                    effective_date: day,
+                    rate: Decimal::new(500 + day.day() as i64, 2),
+                });
+            }
+            day += Duration::days(1);
+        }
+        let mut loan = Loan::new(
+            date(2024, 1, 1),
+            date(2024, 3, 31),
+            Decimal::from(1_000_000),
+            RateSchedule::new(fixings),
+            Decimal::ZERO,
+            CurrencyCode::GBP,
+        )
+        .with_rfr(RfrTerms {
+            payment_delay_days: 2,
+            ..sonia(ObservationMethod::Lookback, 0, 2)
+        });
+        loan.payment_frequency = Some(Frequency::Monthly);
+        loan
+    }
+
+    #[test]
+    fn each_interest_period_compounds_from_its_first_day() {
+        let schedule = Schedule::new(&monthly_loan()).unwrap();
+        let rate_on = |day| schedule.entry_on(day).unwrap().compounded_rate.unwrap();
+        // The first period ends on 1 February and the second starts again
+        // from Friday 2 February's fixing over the weekend
+        assert_eq!(
+            rate_on(date(2024, 2, 2)).cumulative_rate.round_dp(8),
+            Decimal::new(4126027, 8)
+        );
+        assert_eq!(
+            rate_on(date(2024, 2, 2)).daily_rate,
+            rate_on(date(2024, 2, 2)).annualised_rate
+        );
+    }
+
+    #[test]
+    fn lockout_and_payment_delay_apply_at_every_period_end() {
+        let schedule = Schedule::new(&monthly_loan()).unwrap();
+        let observed = |day| {
+            schedule
+                .entry_on(day)
+                .unwrap()
+                .compounded_rate
+                .unwrap()
+                .observation_date
+        };
+        assert_eq!(observed(date(2024, 1, 31)), date(2024, 1, 30));
+        assert_eq!(observed(date(2024, 2, 1)), date(2024, 1, 30));
+        assert_eq!(observed(date(2024, 2, 29)), date(2024, 2, 28));
+        assert_eq!(observed(date(2024, 3, 1)), date(2024, 2, 28));
+        assert_eq!(observed(date(2024, 3, 28)), date(2024, 3, 27));
+        assert_eq!(observed(date(2024, 3, 29)), date(2024, 3, 27));
+
+        let payment_dates: Vec<NaiveDate> = schedule
+            .iter()
+            .filter_map(|entry| entry.payment.as_ref())
+            .map(|payment| payment.payment_date)
+            .collect();
+        assert_eq!(
+            payment_dates,
+            [date(2024, 2, 5), date(2024, 3, 5), date(2024, 4, 2)]
+        );
+    }
+}
@@ -0,0 +1,404 @@
+use std::{error::Error, fmt::Display};
+
+use chrono::NaiveDate;
+
+use crate::currency::CurrencyCode;
+use crate::money::{CurrencyMismatchError, Money};
+
+/// How an amount is brought to the rounding precision
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum RoundingStrategy {
+    /// Banker's rounding, halves go to the even neighbour
+    /// SEE: <https://en.wikipedia.org/wiki/Rounding#Rounding_half_to_even>
+    #[default]
+    HalfEven,
+    /// Halves go away from zero
+    HalfUp,
+    /// Halves go towards zero
+    HalfDown,
+    /// Everything past the precision is dropped
+    Truncate,
+    /// Always towards positive infinity
+    Ceiling,
+    /// Always towards negative infinity
+    Floor,
+}
+
+impl Display for RoundingStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoundingStrategy::HalfEven => f.write_str("half-even"),
+            RoundingStrategy::HalfUp => f.write_str("half-up"),
+            RoundingStrategy::HalfDown => f.write_str("half-down"),
+            RoundingStrategy::Truncate => f.write_str("truncate"),
+            RoundingStrategy::Ceiling => f.write_str("ceiling"),
+            RoundingStrategy::Floor => f.write_str("floor"),
+        }
+    }
+}
+
+/// Rounding strategy that is not recognised
+#[derive(Debug)]
+pub struct UnknownRoundingStrategyError {
+    strategy: String,
+}
+
+impl UnknownRoundingStrategyError {
+    fn new(strategy: String) -> Self {
+        Self { strategy }
+    }
+}
+
+impl Display for UnknownRoundingStrategyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Error unknown rounding strategy: {}",
+            self.strategy
+        ))
+    }
+}
+
+impl Error for UnknownRoundingStrategyError {}
+
+impl TryFrom<&str> for RoundingStrategy {
+    type Error = UnknownRoundingStrategyError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "half-even" | "bankers" => Ok(RoundingStrategy::HalfEven),
+            "half-up" => Ok(RoundingStrategy::HalfUp),
+            "half-down" => Ok(RoundingStrategy::HalfDown),
+            "truncate" | "down" => Ok(RoundingStrategy::Truncate),
+            "ceiling" => Ok(RoundingStrategy::Ceiling),
+            "floor" => Ok(RoundingStrategy::Floor),
+            _ => Err(UnknownRoundingStrategyError::new(value.into())),
+        }
+    }
+}
+
+impl From<RoundingStrategy> for rust_decimal::RoundingStrategy {
+    fn from(strategy: RoundingStrategy) -> Self {
+        match strategy {
+            RoundingStrategy::HalfEven => rust_decimal::RoundingStrategy::MidpointNearestEven,
+            RoundingStrategy::HalfUp => rust_decimal::RoundingStrategy::MidpointAwayFromZero,
+            RoundingStrategy::HalfDown => rust_decimal::RoundingStrategy::MidpointTowardZero,
+            RoundingStrategy::Truncate => rust_decimal::RoundingStrategy::ToZero,
+            RoundingStrategy::Ceiling => rust_decimal::RoundingStrategy::ToPositiveInfinity,
+            RoundingStrategy::Floor => rust_decimal::RoundingStrategy::ToNegativeInfinity,
+        }
+    }
+}
+
+/// Which amounts are rounded, anything not rounded keeps its full precision
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum RoundingStage {
+    /// Each day's accrual is rounded and the rounded days add up to the
+    /// period and total
+    #[default]
+    Daily,
+    /// Each period's accrual is rounded when it is paid, the difference is
+    /// dropped
+    Period,
+    /// Only the total is rounded, period payments carry their rounding
+    /// difference into the next period so they add up to the total
+    Total,
+}
+
+impl Display for RoundingStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RoundingStage::Daily => f.write_str("daily"),
+            RoundingStage::Period => f.write_str("period"),
+            RoundingStage::Total => f.write_str("total"),
+        }
+    }
+}
+
+/// Rounding stage that is not recognised
+#[derive(Debug)]
+pub struct UnknownRoundingStageError {
+    stage: String,
+}
+
+impl UnknownRoundingStageError {
+    fn new(stage: String) -> Self {
+        Self { stage }
+    }
+}
+
+impl Display for UnknownRoundingStageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("Error unknown rounding stage: {}", self.stage))
+    }
+}
+
+impl Error for UnknownRoundingStageError {}
+
+impl TryFrom<&str> for RoundingStage {
+    type Error = UnknownRoundingStageError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "daily" => Ok(RoundingStage::Daily),
+            "period" => Ok(RoundingStage::Period),
+            "total" => Ok(RoundingStage::Total),
+            _ => Err(UnknownRoundingStageError::new(value.into())),
+        }
+    }
+}
+
+/// How and where interest and fees are rounded
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct RoundingPolicy {
+    /// How amounts are rounded
+    pub strategy: RoundingStrategy,
+    /// Decimal places, the currency's minor units when not set
+    pub precision: Option<u32>,
+    /// Which amounts are rounded
+    pub stage: RoundingStage,
+}
+
+impl RoundingPolicy {
+    /// Rounds `money` with the strategy, amounts in currencies without minor
+    /// units are left as they are unless a precision is set
+    pub fn round(&self, money: Money) -> Money {
+        match self.precision.or(money.code.minor_units()) {
+            Some(precision) => Money::new(
+                money
+                    .value
+                    .round_dp_with_strategy(precision, self.strategy.into()),
+                money.code,
+            ),
+            None => money,
+        }
+    }
+
+    /// A day's accrual, rounded when rounding daily
+    pub fn round_daily(&self, money: Money) -> Money {
+        match self.stage {
+            RoundingStage::Daily => self.round(money),
+            RoundingStage::Period | RoundingStage::Total => money,
+        }
+    }
+
+    /// A period's accrual from its `daily` amounts, rounded as the stage
+    /// requires
+    pub fn round_period(
+        &self,
+        code: CurrencyCode,
+        daily: impl IntoIterator<Item = Money>,
+    ) -> Result<Money, CurrencyMismatchError> {
+        let total = Money::sum(code, daily.into_iter().map(|day| self.round_daily(day)))?;
+        Ok(match self.stage {
+            RoundingStage::Daily | RoundingStage::Total => total,
+            RoundingStage::Period => self.round(total),
+        })
+    }
+
+    /// The total of the `periods` amounts, rounded as the stage requires
+    pub fn round_total(
+        &self,
+        code: CurrencyCode,
+        periods: impl IntoIterator<Item = Money>,
+    ) -> Result<Money, CurrencyMismatchError> {
+        let total = Money::sum(code, periods)?;
+        Ok(match self.stage {
+            RoundingStage::Daily | RoundingStage::Period => total,
+            RoundingStage::Total => self.round(total),
+        })
+    }
+}
+
+/// Interest of a period, or of the whole loan, before and after rounding
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoundingDifference {
+    /// End of the period, `None` for the total
+    pub period_end: Option<NaiveDate>,
+    /// Interest at full precision
+    pub unrounded: Money,
+    /// Interest after rounding
+    pub rounded: Money,
+}
+
+impl RoundingDifference {
+    /// Amount rounding added, negative when rounding took interest away
+    pub fn difference(&self) -> Result<Money, CurrencyMismatchError> {
+        self.rounded - self.unrounded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn gbp(value: i64, scale: u32) -> Money {
+        Money::new(Decimal::new(value, scale), CurrencyCode::GBP)
+    }
+
+    // `values` in thousandths of a pound rounded to pence with `strategy`
+    fn rounded(strategy: RoundingStrategy, values: [i64; 4]) -> [Decimal; 4] {
+        let policy = RoundingPolicy {
+            strategy,
+            ..RoundingPolicy::default()
+        };
+        values.map(|value| policy.round(gbp(value, 3)).value)
+    }
+
+    fn pence(values: [i64; 4]) -> [Decimal; 4] {
+        values.map(|value| Decimal::new(value, 2))
+    }
+
+    #[test]
+    fn each_strategy_rounds_halves_and_the_rest_its_own_way() {
+        let values = [2_345, 2_355, -2_345, 2_341];
+        assert_eq!(
+            rounded(RoundingStrategy::HalfEven, values),
+            pence([234, 236, -234, 234])
+        );
+        assert_eq!(
+            rounded(RoundingStrategy::HalfUp, values),
+            pence([235, 236, -235, 234])
+        );
+        assert_eq!(
+            rounded(RoundingStrategy::HalfDown, values),
+            pence([234, 235, -234, 234])
+        );
+        assert_eq!(
+            rounded(RoundingStrategy::Truncate, values),
+            pence([234, 235, -234, 234])
+        );
+        assert_eq!(
+            rounded(RoundingStrategy::Ceiling, values),
+            pence([235, 236, -234, 235])
+        );
+        assert_eq!(
+            rounded(RoundingStrategy::Floor, values),
+            pence([234, 235, -235, 234])
+        );
+    }
+
+    #[test]
+    fn precision_defaults_to_the_currencys_minor_units() {
+        let policy = RoundingPolicy::default();
+        let jpy = Money::new(Decimal::new(12_345, 1), CurrencyCode::JPY);
+        assert_eq!(policy.round(jpy).value, Decimal::from(1_234));
+        let kwd = Money::new(Decimal::new(12_345, 4), CurrencyCode::KWD);
+        assert_eq!(policy.round(kwd).value, Decimal::new(1_234, 3));
+
+        let precise = RoundingPolicy {
+            precision: Some(4),
+            ..RoundingPolicy::default()
+        };
+        assert_eq!(
+            precise.round(gbp(123_456, 5)).value,
+            Decimal::new(12_346, 4)
+        );
+    }
+
+    #[test]
+    fn currencies_without_minor_units_are_rounded_only_to_a_set_precision() {
+        let xau = Money::new(Decimal::new(123_456, 5), CurrencyCode::XAU);
+        assert_eq!(RoundingPolicy::default().round(xau), xau);
+        let precise = RoundingPolicy {
+            precision: Some(2),
+            ..RoundingPolicy::default()
+        };
+        assert_eq!(precise.round(xau).value, Decimal::new(123, 2));
+    }
+
+    #[test]
+    fn each_stage_rounds_its_own_amounts() {
+        // Three days of 0.004 are 0.012: nothing when each day is rounded,
+        // a penny when the period or total is
+        let days = [gbp(4, 3), gbp(4, 3), gbp(4, 3)];
+        let policy = |stage| RoundingPolicy {
+            stage,
+            ..RoundingPolicy::default()
+        };
+
+        let daily = policy(RoundingStage::Daily);
+        assert_eq!(daily.round_daily(gbp(4, 3)), gbp(0, 2));
+        let period = daily.round_period(CurrencyCode::GBP, days).unwrap();
+        assert_eq!(period, gbp(0, 2));
+
+        let period_stage = policy(RoundingStage::Period);
+        assert_eq!(period_stage.round_daily(gbp(4, 3)), gbp(4, 3));
+        let period = period_stage.round_period(CurrencyCode::GBP, days).unwrap();
+        assert_eq!(period, gbp(1, 2));
+        assert_eq!(
+            period_stage
+                .round_total(CurrencyCode::GBP, [gbp(12, 3), gbp(12, 3)])
+                .unwrap(),
+            gbp(24, 3)
+        );
+
+        let total = policy(RoundingStage::Total);
+        let period = total.round_period(CurrencyCode::GBP, days).unwrap();
+        assert_eq!(period, gbp(12, 3));
+        assert_eq!(
+            total
+                .round_total(CurrencyCode::GBP, [period, period])
+                .unwrap(),
+            gbp(2, 2)
+        );
+    }
+
+    #[test]
+    fn rounding_differences_are_what_rounding_added() {
+        let difference = RoundingDifference {
+            period_end: None,
+            unrounded: gbp(12_345, 3),
+            rounded: gbp(1_234, 2),
+        };
+        assert_eq!(difference.difference().unwrap(), gbp(-5, 3));
+    }
+
+    #[test]
+    fn strategies_and_stages_parse_and_display() {
+        for strategy in [
+            RoundingStrategy::HalfEven,
+            RoundingStrategy::HalfUp,
+            RoundingStrategy::HalfDown,
+            RoundingStrategy::Truncate,
+            RoundingStrategy::Ceiling,
+            RoundingStrategy::Floor,
+        ] {
+            assert_eq!(
+                RoundingStrategy::try_from(strategy.to_string().as_str()).unwrap(),
+                strategy
+            );
+        }
+        for stage in [
+            RoundingStage::Daily,
+            RoundingStage::Period,
+            RoundingStage::Total,
+        ] {
+            assert_eq!(
+                RoundingStage::try_from(stage.to_string().as_str()).unwrap(),
+                stage
+            );
+        }
+        assert_eq!(
+            RoundingStrategy::try_from("Bankers").unwrap(),
+            RoundingStrategy::HalfEven
+        );
+        assert_eq!(
+            RoundingStrategy::try_from("up").unwrap_err().to_string(),
+            "Error unknown rounding strategy: up"
+        );
+        assert_eq!(
+            RoundingStage::try_from("monthly").unwrap_err().to_string(),
+            "Error unknown rounding stage: monthly"
+        );
+    }
+}